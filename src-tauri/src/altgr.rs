@@ -0,0 +1,347 @@
+use std::time::{Duration, SystemTime};
+
+use rdev::{Event, EventType, Key};
+
+/// AltGr与它带出的虚拟左Ctrl的最大间隔，Windows在同一次按键中连续发送两个事件
+pub const PHANTOM_CTRL_MAX_INTERVAL: Duration = Duration::from_millis(10);
+
+// 左右Alt的区分：
+// rdev的Key::Alt和Key::AltGr已经按物理位置区分(VK_LMENU/VK_RMENU)，
+// 所以右Alt无论在当前布局下是普通Alt还是AltGr都会显示为r_alt。
+// 需要处理的是Windows在AltGr布局下按AltGr时先发送的一个虚拟左Ctrl，
+// 按住时的重复和松开时也会带上这个左Ctrl。
+// 虚拟左Ctrl的扫描码(KBDLLHOOKSTRUCT.scanCode为0x21D)与真正的左Ctrl不同，
+// 但rdev的钩子不提供扫描码，目前只能按时间判断：
+// 左Ctrl的事件先暂存，PHANTOM_CTRL_MAX_INTERVAL内有AltGr的同类事件时丢弃，否则再发送，
+// 这样虚拟左Ctrl不会被显示或计入统计。
+// Linux(X11和evdev)和macOS的事件没有虚拟左Ctrl，不需要过滤。
+//
+// 待办：按扫描码区分左右修饰键的部分尚未完成，按时间判断只是临时方案。
+// 已知问题：在AltGr之前PHANTOM_CTRL_MAX_INTERVAL内按下的真正的左Ctrl会被当作虚拟按键丢弃。
+// 完成时需要在Windows下自己读取KBDLLHOOKSTRUCT的scanCode和flags(LLKHF_EXTENDED)，
+// 用扫描码判断虚拟左Ctrl并区分左右修饰键，然后去掉这里的时间判断。
+// rdev 0.5的钩子在库内部，不能取到这些字段。
+
+/// AltGr虚拟左Ctrl过滤器
+/// 只依赖事件类型和时间，可以直接输入录制的事件序列进行测试
+pub struct AltGrFilter {
+    /// 暂存的左Ctrl事件，等待判断是否为AltGr带出的虚拟按键
+    pending: Option<Event>,
+    /// AltGr是否按住，且按下时带有虚拟左Ctrl
+    phantom_alt_gr: bool,
+}
+
+impl AltGrFilter {
+    pub fn new() -> Self {
+        Self {
+            pending: None,
+            phantom_alt_gr: false,
+        }
+    }
+
+    /// 暂存的左Ctrl需要发送的时间，没有暂存的事件时为None
+    pub fn deadline(&self) -> Option<SystemTime> {
+        self.pending
+            .as_ref()
+            .map(|pending| pending.time + PHANTOM_CTRL_MAX_INTERVAL)
+    }
+
+    /// 发送超过等待时间的暂存左Ctrl事件
+    pub fn flush(&mut self, now: SystemTime) -> Option<Event> {
+        match self.deadline() {
+            Some(deadline) if now > deadline => self.pending.take(),
+            _ => None,
+        }
+    }
+
+    /// 处理一个输入事件，返回需要按顺序处理的事件
+    pub fn filter(&mut self, event: Event) -> Vec<Event> {
+        let mut events = Vec::new();
+        match event.event_type {
+            // 按下左Ctrl和AltGr时松开的左Ctrl先暂存
+            EventType::KeyPress(Key::ControlLeft) => {
+                events.extend(self.pending.replace(event));
+            }
+            EventType::KeyRelease(Key::ControlLeft) if self.phantom_alt_gr => {
+                events.extend(self.pending.replace(event));
+            }
+            // AltGr按下(包括按住时的重复)前刚按下的左Ctrl为虚拟按键
+            EventType::KeyPress(Key::AltGr) => {
+                if self.take_phantom(&event, true) {
+                    self.phantom_alt_gr = true;
+                }
+                events.extend(self.pending.take());
+                events.push(event);
+            }
+            // AltGr松开前刚松开的左Ctrl为虚拟按键
+            EventType::KeyRelease(Key::AltGr) => {
+                self.take_phantom(&event, false);
+                self.phantom_alt_gr = false;
+                events.extend(self.pending.take());
+                events.push(event);
+            }
+            _ => {
+                events.extend(self.pending.take());
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// 暂存的左Ctrl与AltGr的事件同为按下或松开且间隔足够短时丢弃，返回是否丢弃
+    fn take_phantom(&mut self, alt_gr: &Event, press: bool) -> bool {
+        let phantom = self.pending.as_ref().map_or(false, |pending| {
+            let same_kind = match pending.event_type {
+                EventType::KeyPress(_) => press,
+                _ => !press,
+            };
+            same_kind
+                && alt_gr
+                    .time
+                    .duration_since(pending.time)
+                    .unwrap_or(Duration::ZERO)
+                    <= PHANTOM_CTRL_MAX_INTERVAL
+        });
+        if phantom {
+            self.pending = None;
+        }
+        phantom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use EventType::{KeyPress as Press, KeyRelease as Release};
+
+    /// 模拟的钩子事件序列，每次输入后按时间发送暂存的事件，返回最终处理的事件
+    struct Timeline {
+        filter: AltGrFilter,
+        start: SystemTime,
+        output: Vec<(u64, EventType)>,
+    }
+
+    impl Timeline {
+        fn new() -> Self {
+            Self {
+                filter: AltGrFilter::new(),
+                start: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000),
+                output: Vec::new(),
+            }
+        }
+
+        fn at(&self, ms: u64) -> SystemTime {
+            self.start + Duration::from_millis(ms)
+        }
+
+        fn send(&mut self, ms: u64, event_type: EventType) -> &mut Self {
+            self.tick(ms);
+            let event = Event {
+                time: self.at(ms),
+                name: None,
+                event_type,
+            };
+            for event in self.filter.filter(event) {
+                self.record(event);
+            }
+            self
+        }
+
+        /// 模拟定时发送暂存的事件
+        fn tick(&mut self, ms: u64) {
+            if let Some(event) = self.filter.flush(self.at(ms)) {
+                self.record(event);
+            }
+        }
+
+        fn record(&mut self, event: Event) {
+            let ms = event.time.duration_since(self.start).unwrap().as_millis() as u64;
+            self.output.push((ms, event.event_type));
+        }
+
+        fn finish(&mut self) -> Vec<(u64, EventType)> {
+            self.tick(1_000_000);
+            self.output.clone()
+        }
+    }
+
+    #[test]
+    fn alt_gr_tap() {
+        // Windows在AltGr布局下按下和松开AltGr时的钩子事件
+        let output = Timeline::new()
+            .send(0, Press(Key::ControlLeft))
+            .send(0, Press(Key::AltGr))
+            .send(120, Release(Key::ControlLeft))
+            .send(120, Release(Key::AltGr))
+            .finish();
+        assert_eq!(
+            output,
+            vec![(0, Press(Key::AltGr)), (120, Release(Key::AltGr))]
+        );
+    }
+
+    #[test]
+    fn alt_gr_held_with_repeats() {
+        let output = Timeline::new()
+            .send(0, Press(Key::ControlLeft))
+            .send(1, Press(Key::AltGr))
+            .send(500, Press(Key::ControlLeft))
+            .send(500, Press(Key::AltGr))
+            .send(533, Press(Key::ControlLeft))
+            .send(534, Press(Key::AltGr))
+            .send(600, Release(Key::ControlLeft))
+            .send(601, Release(Key::AltGr))
+            .finish();
+        assert_eq!(
+            output,
+            vec![
+                (1, Press(Key::AltGr)),
+                (500, Press(Key::AltGr)),
+                (534, Press(Key::AltGr)),
+                (601, Release(Key::AltGr)),
+            ]
+        );
+    }
+
+    #[test]
+    fn alt_gr_character() {
+        // AltGr+E输入€
+        let output = Timeline::new()
+            .send(0, Press(Key::ControlLeft))
+            .send(0, Press(Key::AltGr))
+            .send(80, Press(Key::KeyE))
+            .send(150, Release(Key::KeyE))
+            .send(200, Release(Key::ControlLeft))
+            .send(200, Release(Key::AltGr))
+            .finish();
+        assert_eq!(
+            output,
+            vec![
+                (0, Press(Key::AltGr)),
+                (80, Press(Key::KeyE)),
+                (150, Release(Key::KeyE)),
+                (200, Release(Key::AltGr)),
+            ]
+        );
+    }
+
+    #[test]
+    fn real_ctrl_is_delayed_not_dropped() {
+        let mut timeline = Timeline::new();
+        timeline.send(0, Press(Key::ControlLeft));
+        // 等待时间内不发送
+        timeline.tick(10);
+        assert!(timeline.output.is_empty());
+        timeline.tick(11);
+        assert_eq!(timeline.output, vec![(0, Press(Key::ControlLeft))]);
+        let output = timeline
+            .send(100, Press(Key::KeyC))
+            .send(150, Release(Key::KeyC))
+            .send(200, Release(Key::ControlLeft))
+            .finish();
+        assert_eq!(
+            output,
+            vec![
+                (0, Press(Key::ControlLeft)),
+                (100, Press(Key::KeyC)),
+                (150, Release(Key::KeyC)),
+                (200, Release(Key::ControlLeft)),
+            ]
+        );
+    }
+
+    #[test]
+    fn real_ctrl_followed_by_other_key() {
+        // 下一个事件不是AltGr时立即发送暂存的左Ctrl
+        let output = Timeline::new()
+            .send(0, Press(Key::ControlLeft))
+            .send(5, Press(Key::KeyV))
+            .finish();
+        assert_eq!(
+            output,
+            vec![(0, Press(Key::ControlLeft)), (5, Press(Key::KeyV))]
+        );
+    }
+
+    #[test]
+    fn real_ctrl_then_alt_gr() {
+        // 按住左Ctrl后按AltGr，Windows仍会在AltGr前后发送虚拟左Ctrl
+        let output = Timeline::new()
+            .send(0, Press(Key::ControlLeft))
+            .send(300, Press(Key::ControlLeft))
+            .send(300, Press(Key::AltGr))
+            .send(400, Release(Key::ControlLeft))
+            .send(400, Release(Key::AltGr))
+            .send(500, Release(Key::ControlLeft))
+            .finish();
+        assert_eq!(
+            output,
+            vec![
+                (0, Press(Key::ControlLeft)),
+                (300, Press(Key::AltGr)),
+                (400, Release(Key::AltGr)),
+                (500, Release(Key::ControlLeft)),
+            ]
+        );
+    }
+
+    #[test]
+    fn real_ctrl_released_while_alt_gr_held() {
+        let output = Timeline::new()
+            .send(0, Press(Key::ControlLeft))
+            .send(0, Press(Key::AltGr))
+            .send(100, Press(Key::ControlLeft))
+            .send(200, Release(Key::ControlLeft))
+            .send(300, Release(Key::ControlLeft))
+            .send(300, Release(Key::AltGr))
+            .finish();
+        assert_eq!(
+            output,
+            vec![
+                (0, Press(Key::AltGr)),
+                (100, Press(Key::ControlLeft)),
+                (200, Release(Key::ControlLeft)),
+                (300, Release(Key::AltGr)),
+            ]
+        );
+    }
+
+    #[test]
+    fn right_alt_without_phantom_ctrl() {
+        // 没有AltGr的布局下右Alt是普通Alt
+        let output = Timeline::new()
+            .send(0, Press(Key::AltGr))
+            .send(100, Press(Key::Tab))
+            .send(150, Release(Key::Tab))
+            .send(200, Release(Key::AltGr))
+            .finish();
+        assert_eq!(
+            output,
+            vec![
+                (0, Press(Key::AltGr)),
+                (100, Press(Key::Tab)),
+                (150, Release(Key::Tab)),
+                (200, Release(Key::AltGr)),
+            ]
+        );
+    }
+
+    #[test]
+    fn left_ctrl_long_before_alt_gr_is_real() {
+        let output = Timeline::new()
+            .send(0, Press(Key::ControlLeft))
+            .send(11, Press(Key::AltGr))
+            .send(50, Release(Key::AltGr))
+            .send(60, Release(Key::ControlLeft))
+            .finish();
+        assert_eq!(
+            output,
+            vec![
+                (0, Press(Key::ControlLeft)),
+                (11, Press(Key::AltGr)),
+                (50, Release(Key::AltGr)),
+                (60, Release(Key::ControlLeft)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,567 @@
+use std::{
+    io::BufRead,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use tokio::{select, sync::broadcast};
+
+#[cfg(target_os = "linux")]
+use crate::evdev_backend;
+use crate::{
+    gamepad::{self, AxisOptions},
+    generator::{generate_to_dir, GenerateOptions, LayoutTemplate},
+    initialize_config, inputs,
+    message::KeySender,
+    mouse::{self, MotionSender},
+    preset::{check_preset_name, find_preset_dir, user_preset_dir},
+    record::{self, ReplayCommand},
+    server,
+    subtitles::{self, SubtitleFormat, SubtitleOptions},
+    CONFIG,
+};
+
+/// 回放时服务器的默认端口
+const DEFAULT_PORT: u16 = 61477;
+
+static USAGE: &str = "Usage:
+  input_portal preset generate <template> <name> [options]
+  input_portal record <file> [--duration <s>]
+  input_portal replay <file> [--speed <n>] [--step] [--port <port>] [--delay <s>]
+  input_portal subtitles <recording> <output> [--format <format>] [--min-duration <ms>] [--no-chords]
+  input_portal evdev-dump <dump>
+  input_portal gamepad-filter <recording> [--deadzone <n>] [--rate <hz>]
+
+Templates:
+  full_size_ansi, tkl, sixty_percent, iso, wasd, numpad, mouse_3_button, mouse_5_button
+
+Options:
+  --key-size <px>       1u key size (default 54)
+  --gap <px>            gap between keys (default 6)
+  --font <family>       font family (default sans-serif)
+  --font-size <px>      font size (default key size / 3.5)
+  --background <color>  background color
+  --key-color <color>   key color
+  --border-color <color>
+  --text-color <color>
+  --out <dir>           output directory (default ./presets/<name>)
+
+Record options:
+  --duration <s>        stop after the given seconds (default until Ctrl+C)
+
+Replay options:
+  --speed <n>           playback speed (default 1)
+  --step                send one input each time Enter is pressed, q to quit
+  --port <port>         overlay server port (default 61477)
+  --delay <s>           wait before playing so overlays can connect (default 3)
+
+Subtitles options:
+  --format <format>     srt, vtt or ass (default from output extension)
+  --min-duration <ms>   minimum display time of a cue (default 500)
+  --no-chords           show modifiers as separate keys instead of chords
+
+Evdev dump:
+  print the events translated from an evdev dump (Linux only), the dump is
+  JSON lines of {time, kind, code, value} or the output of evtest
+
+Gamepad filter options:
+  apply the stick/trigger deadzone and rate limit to a recording and print the
+  result as JSON lines, record with a deadzone and rate of 0 to keep raw values
+  --deadzone <n>        deadzone 0~1 (default from config)
+  --rate <hz>           max messages per axis per second, 0 for no limit (default from config)
+
+On Windows the release build is a GUI program and cmd.exe does not wait for it,
+run it with `start /wait input_portal ...` to keep the output in order.";
+
+/// 处理命令行参数，不是命令行调用时返回None，否则返回退出码
+pub async fn run(args: &[String]) -> Option<i32> {
+    // 第一个参数为程序路径
+    let command: Vec<&str> = args.iter().skip(1).map(|arg| arg.as_str()).collect();
+    match command.as_slice() {
+        [] => return None,
+        ["preset", "generate", ..]
+        | ["record", ..]
+        | ["replay", ..]
+        | ["subtitles", ..]
+        | ["evdev-dump", ..]
+        | ["gamepad-filter", ..]
+        | ["help" | "--help" | "-h", ..] => attach_console(),
+        // 其他参数(如系统启动时附带的参数)交给tauri
+        _ => return None,
+    }
+    match command.as_slice() {
+        ["preset", "generate", rest @ ..] => Some(preset_generate(rest)),
+        ["record", rest @ ..] => Some(record(rest).await),
+        ["replay", rest @ ..] => Some(replay(rest).await),
+        ["subtitles", rest @ ..] => Some(export_subtitles(rest)),
+        ["evdev-dump", rest @ ..] => Some(evdev_dump(rest)),
+        ["gamepad-filter", rest @ ..] => Some(gamepad_filter(rest)),
+        ["help" | "--help" | "-h", ..] => {
+            println!("{}", USAGE);
+            Some(0)
+        }
+        _ => None,
+    }
+}
+
+/// Windows的release版本没有控制台(windows_subsystem = "windows")，println!的输出不会显示
+/// 命令行调用时连接到启动程序的终端的控制台，从资源管理器等没有控制台的地方启动时不输出
+#[cfg(target_os = "windows")]
+fn attach_console() {
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    // ATTACH_PARENT_PROCESS
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn attach_console() {}
+
+/// input_portal preset generate <template> <name> [options]
+fn preset_generate(args: &[&str]) -> i32 {
+    let (template_name, name) = match args {
+        [template_name, name, ..] => (*template_name, *name),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let template = match LayoutTemplate::from_name(template_name) {
+        Some(template) => template,
+        None => {
+            eprintln!("unknown template: {}", template_name);
+            return 2;
+        }
+    };
+    if let Err(error) = check_preset_name(name) {
+        eprintln!("invalid preset name {}: {:?}", name, error);
+        return 2;
+    }
+    let mut options = GenerateOptions::new(name.to_string(), template);
+    let mut out = user_preset_dir(name);
+    // 解析选项
+    let mut iter = args[2..].iter();
+    while let Some(option) = iter.next() {
+        let value = match iter.next() {
+            Some(value) => *value,
+            None => {
+                eprintln!("missing value for {}", option);
+                return 2;
+            }
+        };
+        let res = match *option {
+            "--key-size" => value.parse().map(|v| options.key_size = v).is_ok(),
+            "--gap" => value.parse().map(|v| options.gap = v).is_ok(),
+            "--font-size" => value.parse().map(|v| options.font_size = Some(v)).is_ok(),
+            "--font" => {
+                options.font_family = value.to_string();
+                true
+            }
+            "--background" => {
+                options.background_color = value.to_string();
+                true
+            }
+            "--key-color" => {
+                options.key_color = value.to_string();
+                true
+            }
+            "--border-color" => {
+                options.border_color = value.to_string();
+                true
+            }
+            "--text-color" => {
+                options.text_color = value.to_string();
+                true
+            }
+            "--out" => {
+                out = PathBuf::from(value);
+                true
+            }
+            _ => {
+                eprintln!("unknown option: {}", option);
+                return 2;
+            }
+        };
+        if !res {
+            eprintln!("invalid value for {}: {}", option, value);
+            return 2;
+        }
+    }
+    // 与已有的内置或用户预设同名时不生成
+    if find_preset_dir(name).is_some() {
+        eprintln!("preset {} already exists", name);
+        return 1;
+    }
+    if out.exists() {
+        eprintln!("{} already exists", out.display());
+        return 1;
+    }
+    match generate_to_dir(&options, &out) {
+        Ok(manifest) => {
+            println!(
+                "generated {} ({}x{}, {} keys) in {}",
+                name,
+                manifest.width,
+                manifest.height,
+                manifest.keys.len(),
+                out.display()
+            );
+            0
+        }
+        Err(error) => {
+            eprintln!("failed to generate preset: {:?}", error);
+            1
+        }
+    }
+}
+
+/// 解析"--name value"形式的选项，flags为不带值的选项
+fn parse_options<'a>(
+    args: &[&'a str],
+    flags: &[&str],
+) -> Result<Vec<(&'a str, Option<&'a str>)>, String> {
+    let mut options = Vec::new();
+    let mut iter = args.iter();
+    while let Some(option) = iter.next() {
+        if flags.contains(option) {
+            options.push((*option, None));
+            continue;
+        }
+        match iter.next() {
+            Some(value) => options.push((*option, Some(*value))),
+            None => return Err(format!("missing value for {}", option)),
+        }
+    }
+    Ok(options)
+}
+
+/// input_portal record <file> [--duration <s>]
+async fn record(args: &[&str]) -> i32 {
+    let path = match args.first() {
+        Some(path) => Path::new(path),
+        None => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let mut duration = None;
+    let options = match parse_options(&args[1..], &[]) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 2;
+        }
+    };
+    for (option, value) in options {
+        let value = value.unwrap();
+        match option {
+            "--duration" => match value.parse::<f64>() {
+                Ok(secs) if secs.is_finite() && secs > 0.0 => {
+                    duration = Some(Duration::from_secs_f64(secs))
+                }
+                _ => {
+                    eprintln!("invalid value for {}: {}", option, value);
+                    return 2;
+                }
+            },
+            _ => {
+                eprintln!("unknown option: {}", option);
+                return 2;
+            }
+        }
+    }
+
+    initialize_config();
+    let (sender, _) = broadcast::channel(256);
+    let key_sender = KeySender::new();
+    let (motion_sender, _) = broadcast::channel(256);
+    let motion_sender = MotionSender(motion_sender);
+    let input_sender = sender.clone();
+    let input_key_sender = key_sender.clone();
+    tokio::task::spawn_blocking(move || inputs::start(input_sender, input_key_sender));
+    tokio::task::spawn(mouse::run(motion_sender.clone()));
+    if let Err(error) = record::start_recording(
+        &sender,
+        &key_sender,
+        &motion_sender,
+        Some(path.to_path_buf()),
+    ) {
+        eprintln!("failed to start recording: {:?}", error);
+        return 1;
+    }
+    println!("recording to {}, press Ctrl+C to stop", path.display());
+    match duration {
+        Some(duration) => select! {
+            _ = tokio::time::sleep(duration) => {},
+            _ = tokio::signal::ctrl_c() => {},
+        },
+        None => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+    match record::stop_recording().await {
+        Ok((path, count)) => {
+            println!("recorded {} inputs to {}", count, path.display());
+            0
+        }
+        Err(error) => {
+            eprintln!("failed to stop recording: {:?}", error);
+            1
+        }
+    }
+}
+
+/// input_portal replay <file> [--speed <n>] [--step] [--port <port>] [--delay <s>]
+async fn replay(args: &[&str]) -> i32 {
+    let path = match args.first() {
+        Some(path) => Path::new(path),
+        None => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let mut speed = 1.0;
+    let mut step = false;
+    let mut port = DEFAULT_PORT;
+    let mut delay = 3.0;
+    let options = match parse_options(&args[1..], &["--step"]) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 2;
+        }
+    };
+    for (option, value) in options {
+        let res = match (option, value) {
+            ("--step", _) => {
+                step = true;
+                true
+            }
+            ("--speed", Some(value)) => value.parse().map(|v| speed = v).is_ok(),
+            ("--port", Some(value)) => value.parse().map(|v| port = v).is_ok(),
+            ("--delay", Some(value)) => {
+                value.parse::<f64>().map(|v| delay = v).is_ok() && delay.is_finite() && delay >= 0.0
+            }
+            _ => {
+                eprintln!("unknown option: {}", option);
+                return 2;
+            }
+        };
+        if !res {
+            eprintln!("invalid value for {}: {}", option, value.unwrap_or(""));
+            return 2;
+        }
+    }
+    if !port_check::is_local_port_free(port) {
+        eprintln!("port {} is in use", port);
+        return 1;
+    }
+
+    initialize_config();
+    let (sender, _) = broadcast::channel(256);
+    let key_sender = KeySender::new();
+    let (motion_sender, _) = broadcast::channel(256);
+    let motion_sender = MotionSender(motion_sender);
+    tokio::task::spawn(mouse::run(motion_sender.clone()));
+    tokio::task::spawn(server::run(
+        sender.clone(),
+        key_sender.clone(),
+        motion_sender.clone(),
+        port,
+    ));
+    println!("overlay server listening on http://localhost:{}", port);
+    if !step {
+        tokio::time::sleep(Duration::from_secs_f64(delay)).await;
+    }
+    let task = match record::start_replay(&sender, &key_sender, &motion_sender, path, speed, step) {
+        Ok(task) => task,
+        Err(error) => {
+            eprintln!("failed to replay {}: {:?}", path.display(), error);
+            return 1;
+        }
+    };
+    if step {
+        println!("press Enter to send the next input, q to quit");
+        // 标准输入控制步进
+        tokio::task::spawn_blocking(|| {
+            for line in std::io::stdin().lock().lines() {
+                let command = match line {
+                    Ok(line) if line.trim().eq("q") => ReplayCommand::Stop,
+                    Ok(_) => ReplayCommand::Step,
+                    Err(_) => ReplayCommand::Stop,
+                };
+                let stop = matches!(command, ReplayCommand::Stop);
+                if record::control_replay(command).is_err() || stop {
+                    break;
+                }
+            }
+        });
+    }
+    select! {
+        _ = task => {},
+        _ = tokio::signal::ctrl_c() => {
+            let _ = record::control_replay(ReplayCommand::Stop);
+            // 等待按住的按键被释放
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        },
+    }
+    println!("replay finished");
+    0
+}
+
+/// input_portal subtitles <recording> <output> [--format <format>] [--min-duration <ms>] [--no-chords]
+fn export_subtitles(args: &[&str]) -> i32 {
+    let (recording, output) = match args {
+        [recording, output, ..] => (Path::new(recording), Path::new(output)),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let mut options = SubtitleOptions {
+        format: SubtitleFormat::Srt,
+        min_duration_ms: subtitles::DEFAULT_MIN_DURATION_MS,
+        merge_chords: true,
+    };
+    let mut format = SubtitleFormat::from_path(output);
+    let parsed = match parse_options(&args[2..], &["--no-chords"]) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 2;
+        }
+    };
+    for (option, value) in parsed {
+        let res = match (option, value) {
+            ("--no-chords", _) => {
+                options.merge_chords = false;
+                true
+            }
+            ("--format", Some(value)) => {
+                format = SubtitleFormat::from_name(value);
+                format.is_some()
+            }
+            ("--min-duration", Some(value)) => {
+                value.parse().map(|v| options.min_duration_ms = v).is_ok()
+            }
+            _ => {
+                eprintln!("unknown option: {}", option);
+                return 2;
+            }
+        };
+        if !res {
+            eprintln!("invalid value for {}: {}", option, value.unwrap_or(""));
+            return 2;
+        }
+    }
+    options.format = match format {
+        Some(format) => format,
+        None => {
+            eprintln!("unknown subtitle format, use --format srt|vtt|ass");
+            return 2;
+        }
+    };
+    match subtitles::export(recording, output, &options) {
+        Ok(count) => {
+            println!("exported {} cues to {}", count, output.display());
+            0
+        }
+        Err(error) => {
+            eprintln!("failed to export subtitles: {:?}", error);
+            1
+        }
+    }
+}
+
+/// input_portal gamepad-filter <recording> [--deadzone <n>] [--rate <hz>]
+fn gamepad_filter(args: &[&str]) -> i32 {
+    let path = match args.first() {
+        Some(path) => Path::new(path),
+        None => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let options = match parse_options(&args[1..], &[]) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}", error);
+            return 2;
+        }
+    };
+    initialize_config();
+    let mut axis_options = unsafe {
+        let config = CONFIG.lock().unwrap();
+        AxisOptions {
+            deadzone: config.gamepad_deadzone,
+            rate_hz: config.gamepad_axis_rate_hz,
+        }
+    };
+    for (option, value) in options {
+        let value = value.unwrap();
+        let res = match option {
+            "--deadzone" => value.parse().map(|v| axis_options.deadzone = v).is_ok(),
+            "--rate" => value.parse().map(|v| axis_options.rate_hz = v).is_ok(),
+            _ => {
+                eprintln!("unknown option: {}", option);
+                return 2;
+            }
+        };
+        if !res {
+            eprintln!("invalid value for {}: {}", option, value);
+            return 2;
+        }
+    }
+    let inputs = match record::read_recording(path) {
+        Ok(inputs) => inputs,
+        Err(error) => {
+            eprintln!("failed to read {}: {:?}", path.display(), error);
+            return 1;
+        }
+    };
+    for output in gamepad::replay_axes(&inputs, &axis_options) {
+        println!("{}", serde_json::to_string(&output).unwrap());
+    }
+    0
+}
+
+/// input_portal evdev-dump <dump>
+#[cfg(target_os = "linux")]
+fn evdev_dump(args: &[&str]) -> i32 {
+    let path = match args {
+        [path] => Path::new(path),
+        _ => {
+            eprintln!("{}", USAGE);
+            return 2;
+        }
+    };
+    let events = match evdev_backend::read_dump(path) {
+        Ok(events) => events,
+        Err(error) => {
+            eprintln!("failed to read {}: {:?}", path.display(), error);
+            return 1;
+        }
+    };
+    let mut translator = evdev_backend::Translator::new();
+    for raw in events.iter() {
+        if let Some(event) = translator.translate(raw) {
+            let time = event
+                .time
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |time| time.as_millis());
+            println!("{} {:?}", time, event.event_type);
+        }
+    }
+    0
+}
+
+#[cfg(not(target_os = "linux"))]
+fn evdev_dump(_args: &[&str]) -> i32 {
+    eprintln!("evdev-dump is only available on Linux");
+    1
+}
@@ -1,5 +1,30 @@
 use serde::{Deserialize, Serialize};
 
+use crate::preset::PresetDefaults;
+
+/// 输入监听方式
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputBackend {
+    /// Linux下Wayland会话使用evdev，其他情况使用rdev
+    #[default]
+    Auto,
+    Rdev,
+    /// 直接读取/dev/input/event*，仅Linux
+    Evdev,
+}
+
+/// 预设区域绑定的按键名
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyBindMode {
+    /// 按键的物理位置(keys.rs中的名字)，与键盘布局无关
+    #[default]
+    Physical,
+    /// 当前键盘布局下按键输入的字符，没有字符时使用物理位置
+    Label,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     // 预设图片路径
@@ -20,6 +45,147 @@ pub struct Config {
     pub mouse_move_radius_px: u64,
     // 鼠标移动时动画的过渡时间(ms)
     pub mouse_move_transition_duration: u64,
+    // 鼠标移动消息的最大发送频率(Hz)，超出时只发送最新的位置，为0时不限制
+    #[serde(default = "default_mouse_move_rate_hz")]
+    pub mouse_move_rate_hz: u64,
+    // 只显示该序号的显示器上的鼠标移动，为空时显示所有显示器
+    #[serde(default)]
+    pub mouse_monitor: Option<usize>,
+    // 切换预设时保留用户修改过的设置，不使用预设推荐值
+    #[serde(default)]
+    pub keep_user_overrides: bool,
+    // 需要高亮的组合键，如"ctrl+shift+s"
+    #[serde(default)]
+    pub highlight_chords: Vec<String>,
+    // 按键记录(key-cast)开关
+    #[serde(default)]
+    pub key_cast_enable: bool,
+    // 按键记录中显示输入的字符并合并为单词，关闭时字符会被遮挡
+    #[serde(default)]
+    pub key_cast_show_text: bool,
+    // 按键记录无输入后开始新片段的时间(ms)
+    #[serde(default = "default_key_cast_timeout_ms")]
+    pub key_cast_timeout_ms: u64,
+    // 统计数据(KPS、APM等)的广播间隔(ms)，为0时不广播
+    #[serde(default = "default_stats_interval_ms")]
+    pub stats_interval_ms: u64,
+    // 手势(短按、双击、长按)消息开关
+    #[serde(default)]
+    pub gesture_enable: bool,
+    // 按住不超过该时间视为短按(ms)
+    #[serde(default = "default_gesture_tap_max_ms")]
+    pub gesture_tap_max_ms: u64,
+    // 两次短按间隔不超过该时间视为双击(ms)
+    #[serde(default = "default_gesture_double_tap_interval_ms")]
+    pub gesture_double_tap_interval_ms: u64,
+    // 按住不少于该时间视为长按(ms)
+    #[serde(default = "default_gesture_long_press_ms")]
+    pub gesture_long_press_ms: u64,
+    // 手柄输入开关
+    #[serde(default)]
+    pub gamepad_enable: bool,
+    // 手柄摇杆和扳机的死区(0~1)
+    #[serde(default = "default_gamepad_deadzone")]
+    pub gamepad_deadzone: f64,
+    // 手柄每个轴的最大发送频率(Hz)，为0时不限制
+    #[serde(default = "default_gamepad_axis_rate_hz")]
+    pub gamepad_axis_rate_hz: u64,
+    // 输入监听方式，重启后生效
+    #[serde(default)]
+    pub input_backend: InputBackend,
+    // 使用evdev时读取的设备名或路径，为空时读取所有键盘和鼠标，重启后生效
+    #[serde(default)]
+    pub input_devices: Vec<String>,
+    // 按键按住超过该时间(ms)时视为松开事件丢失并自动松开，为0时不检查
+    // 正在重复的按键从最后一次重复按下开始计算，修饰键和鼠标按键不会重复，从按下开始计算
+    #[serde(default = "default_max_key_hold_ms")]
+    pub max_key_hold_ms: u64,
+    // 只在这些应用获得焦点时发送输入，匹配窗口类名或标题，为空时不限制
+    #[serde(default)]
+    pub app_allow_list: Vec<String>,
+    // 这些应用获得焦点时暂停发送输入，匹配窗口类名或标题
+    #[serde(default)]
+    pub app_deny_list: Vec<String>,
+    // 是否允许通过接口注入模拟输入，用于测试预设
+    // 注意：服务器监听0.0.0.0且没有认证，开启后局域网内的任何设备都可以注入输入，
+    // 注入的输入会显示在叠加层上并计入统计，只在可信的网络中开启
+    #[serde(default)]
+    pub injection_enable: bool,
+    // 预设区域绑定物理按键名还是键盘布局下的字符
+    #[serde(default)]
+    pub key_bind_mode: KeyBindMode,
+}
+
+fn default_mouse_move_rate_hz() -> u64 {
+    60
+}
+
+fn default_key_cast_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_max_key_hold_ms() -> u64 {
+    30000
+}
+
+fn default_stats_interval_ms() -> u64 {
+    1000
+}
+
+fn default_gamepad_deadzone() -> f64 {
+    0.1
+}
+
+fn default_gamepad_axis_rate_hz() -> u64 {
+    60
+}
+
+fn default_gesture_tap_max_ms() -> u64 {
+    200
+}
+
+fn default_gesture_double_tap_interval_ms() -> u64 {
+    300
+}
+
+fn default_gesture_long_press_ms() -> u64 {
+    500
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            preset: None,
+            enable: true,
+            key_down_transition_duration: 0,
+            key_up_transition_duration: 100,
+            mouse_move_enable: true,
+            mouse_move_radius_px: 50,
+            mouse_move_transition_duration: 100,
+            mouse_move_rate_hz: default_mouse_move_rate_hz(),
+            mouse_monitor: None,
+            keep_user_overrides: false,
+            highlight_chords: Vec::new(),
+            key_cast_enable: false,
+            key_cast_show_text: false,
+            key_cast_timeout_ms: default_key_cast_timeout_ms(),
+            stats_interval_ms: default_stats_interval_ms(),
+            gesture_enable: false,
+            gesture_tap_max_ms: default_gesture_tap_max_ms(),
+            gesture_double_tap_interval_ms: default_gesture_double_tap_interval_ms(),
+            gesture_long_press_ms: default_gesture_long_press_ms(),
+            gamepad_enable: false,
+            gamepad_deadzone: default_gamepad_deadzone(),
+            gamepad_axis_rate_hz: default_gamepad_axis_rate_hz(),
+            input_backend: InputBackend::Auto,
+            input_devices: Vec::new(),
+            max_key_hold_ms: default_max_key_hold_ms(),
+            app_allow_list: Vec::new(),
+            app_deny_list: Vec::new(),
+            injection_enable: false,
+            key_bind_mode: KeyBindMode::Physical,
+        }
+    }
 }
 
 impl Config {
@@ -31,5 +197,65 @@ impl Config {
         self.mouse_move_enable = config.mouse_move_enable;
         self.mouse_move_radius_px = config.mouse_move_radius_px;
         self.mouse_move_transition_duration = config.mouse_move_transition_duration;
+        self.mouse_move_rate_hz = config.mouse_move_rate_hz;
+        self.mouse_monitor = config.mouse_monitor;
+        self.keep_user_overrides = config.keep_user_overrides;
+        self.highlight_chords = config.highlight_chords;
+        self.key_cast_enable = config.key_cast_enable;
+        self.key_cast_show_text = config.key_cast_show_text;
+        self.key_cast_timeout_ms = config.key_cast_timeout_ms;
+        self.stats_interval_ms = config.stats_interval_ms;
+        self.gesture_enable = config.gesture_enable;
+        self.gesture_tap_max_ms = config.gesture_tap_max_ms;
+        self.gesture_double_tap_interval_ms = config.gesture_double_tap_interval_ms;
+        self.gesture_long_press_ms = config.gesture_long_press_ms;
+        self.gamepad_enable = config.gamepad_enable;
+        self.gamepad_deadzone = config.gamepad_deadzone;
+        self.gamepad_axis_rate_hz = config.gamepad_axis_rate_hz;
+        self.input_backend = config.input_backend;
+        self.input_devices = config.input_devices;
+        self.max_key_hold_ms = config.max_key_hold_ms;
+        self.app_allow_list = config.app_allow_list;
+        self.app_deny_list = config.app_deny_list;
+        self.injection_enable = config.injection_enable;
+        self.key_bind_mode = config.key_bind_mode;
+    }
+
+    /// 切换预设时应用新预设的推荐设置
+    /// 与旧预设推荐值(没有则为全局默认值)不同的项视为用户修改过的设置
+    pub fn apply_preset_defaults(&mut self, old: &PresetDefaults, new: &PresetDefaults) -> () {
+        let default = Config::default();
+        let keep = self.keep_user_overrides;
+        let pick = |current: u64, old: Option<u64>, new: Option<u64>, default: u64| {
+            if keep && current != old.unwrap_or(default) {
+                current
+            } else {
+                new.unwrap_or(default)
+            }
+        };
+        self.key_down_transition_duration = pick(
+            self.key_down_transition_duration,
+            old.key_down_transition_duration,
+            new.key_down_transition_duration,
+            default.key_down_transition_duration,
+        );
+        self.key_up_transition_duration = pick(
+            self.key_up_transition_duration,
+            old.key_up_transition_duration,
+            new.key_up_transition_duration,
+            default.key_up_transition_duration,
+        );
+        self.mouse_move_radius_px = pick(
+            self.mouse_move_radius_px,
+            old.mouse_move_radius_px,
+            new.mouse_move_radius_px,
+            default.mouse_move_radius_px,
+        );
+        self.mouse_move_transition_duration = pick(
+            self.mouse_move_transition_duration,
+            old.mouse_move_transition_duration,
+            new.mouse_move_transition_duration,
+            default.mouse_move_transition_duration,
+        );
     }
 }
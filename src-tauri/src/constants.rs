@@ -1,2 +1,12 @@
 pub static PRESET_IMAGE_FILE_NAME: &str = "image.png";
-pub static PRESET_CONFIG_FILE_NAME: &str = "config.json";
\ No newline at end of file
+pub static PRESET_CONFIG_FILE_NAME: &str = "config.json";
+/// 生成预设时额外输出的矢量图
+pub static PRESET_SVG_FILE_NAME: &str = "image.svg";
+/// 内置预设所在的资源目录
+pub static PRESETS_RESOURCE_DIR: &str = "presets";
+/// 用户预设目录，编辑器的读写都在这里进行
+pub static USER_PRESETS_DIR: &str = "./presets";
+/// 按键次数记录文件
+pub static USAGE_FILE_PATH: &str = "./usage.json";
+/// 输入录制文件的默认目录
+pub static RECORDINGS_DIR: &str = "./recordings";
@@ -0,0 +1,114 @@
+use std::{collections::BTreeMap, path::Path, sync::Mutex};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inputs::{InputInfo, InputSource},
+    preset::{resolve_manifest, PresetManifest},
+    record::read_recording,
+    CONFIG,
+};
+
+#[derive(Debug, Serialize)]
+pub enum CoverageError {
+    NoPresetError,
+    PresetError,
+    RecordingError,
+}
+
+/// 预设按键覆盖检查结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// 检查的预设名
+    pub preset: String,
+    /// 按下过但预设中没有区域的按键和按下次数
+    pub unbound_keys: BTreeMap<String, u64>,
+    /// 预设中有区域但没有按下过的按键
+    pub unused_regions: Vec<String>,
+    /// 按下过且预设中有区域的按键
+    pub covered_keys: Vec<String>,
+}
+
+/// 启动或重置后按下过的键盘按键和鼠标按键(预设绑定的按键名)和次数
+static SEEN: Lazy<Mutex<BTreeMap<String, u64>>> = Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+/// 记录一次按下，由Handler在按键按下时调用
+pub fn record(name: &str, source: &InputSource) {
+    if let InputSource::Keyboard | InputSource::MouseButton = source {
+        *SEEN.lock().unwrap().entry(name.to_string()).or_insert(0) += 1;
+    }
+}
+
+/// 清除记录的按键
+pub fn reset() {
+    SEEN.lock().unwrap().clear();
+}
+
+/// 比较按下过的按键和预设的按键区域
+pub fn check(
+    preset: &str,
+    manifest: &PresetManifest,
+    seen: &BTreeMap<String, u64>,
+) -> CoverageReport {
+    let mut report = CoverageReport {
+        preset: preset.to_string(),
+        ..Default::default()
+    };
+    for (name, count) in seen.iter() {
+        if manifest.keys.iter().any(|region| region.name.eq(name)) {
+            report.covered_keys.push(name.clone());
+        } else {
+            report.unbound_keys.insert(name.clone(), *count);
+        }
+    }
+    for region in manifest.keys.iter() {
+        if !seen.contains_key(&region.name) && !report.unused_regions.contains(&region.name) {
+            report.unused_regions.push(region.name.clone());
+        }
+    }
+    report
+}
+
+/// 统计录制文件中按下过的按键
+pub fn read_seen_keys(path: &Path) -> Result<BTreeMap<String, u64>, CoverageError> {
+    let inputs = read_recording(path).map_err(|_| CoverageError::RecordingError)?;
+    let mut seen = BTreeMap::new();
+    for input in inputs {
+        if let InputSource::Keyboard | InputSource::MouseButton = input.message.source {
+            if let InputInfo::Pressing {
+                name,
+                pressing: true,
+                ..
+            } = input.message.info
+            {
+                *seen.entry(name).or_insert(0) += 1;
+            }
+        }
+    }
+    Ok(seen)
+}
+
+/// 检查当前预设，recording为录制文件路径，为None时使用启动或重置后按下过的按键
+pub fn check_current(recording: Option<&Path>) -> Result<CoverageReport, CoverageError> {
+    let preset =
+        unsafe { CONFIG.lock().unwrap().preset.clone() }.ok_or(CoverageError::NoPresetError)?;
+    let manifest = resolve_manifest(&preset).map_err(|_| CoverageError::PresetError)?;
+    let seen = match recording {
+        Some(path) => read_seen_keys(path)?,
+        None => SEEN.lock().unwrap().clone(),
+    };
+    Ok(check(&preset, &manifest, &seen))
+}
+
+/// 前端获取当前预设的按键覆盖情况
+#[tauri::command]
+pub fn get_preset_coverage(recording: Option<String>) -> Result<CoverageReport, CoverageError> {
+    check_current(recording.as_ref().map(Path::new))
+}
+
+/// 清除记录的按键，重新开始检查
+#[tauri::command]
+pub fn reset_preset_coverage() {
+    reset()
+}
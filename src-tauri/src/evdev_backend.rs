@@ -0,0 +1,570 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use evdev::{Device, Key as EvdevKey, RelativeAxisType};
+use once_cell::sync::Lazy;
+use rdev::{Button, Event, EventType, Key};
+use serde::{Deserialize, Serialize};
+use serde_with::{formats::Flexible, serde_as, TimestampMilliSeconds};
+
+use crate::{
+    config::InputBackend,
+    inputs::{Handler, InputDevice},
+    monitor, CONFIG,
+};
+
+/// 输入设备目录
+const INPUT_DIR: &str = "/dev/input";
+/// 扫描新设备(热插拔)的间隔
+const SCAN_INTERVAL: Duration = Duration::from_secs(2);
+
+/// evdev事件类型
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_MSC: u16 = 0x04;
+/// 相对轴
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+/// 鼠标按键
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BTN_SIDE: u16 = 0x113;
+/// BTN_MOUSE范围的最后一个按键(BTN_TASK)
+const BTN_TASK: u16 = 0x117;
+
+/// 设备状态，键为设备路径
+static DEVICES: Lazy<Mutex<BTreeMap<PathBuf, InputDevice>>> =
+    Lazy::new(|| Mutex::new(BTreeMap::new()));
+
+#[derive(Debug, Serialize)]
+pub enum DumpError {
+    ReadFileError,
+    InvalidDumpError,
+}
+
+/// 原始evdev事件，可以从事件转储中读取用于重现问题
+#[serde_as]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RawEvent {
+    #[serde_as(as = "TimestampMilliSeconds<String, Flexible>")]
+    pub time: SystemTime,
+    /// 事件类型(EV_KEY等)
+    pub kind: u16,
+    pub code: u16,
+    pub value: i32,
+}
+
+/// 是否使用evdev读取输入
+pub fn should_use() -> bool {
+    match unsafe { CONFIG.lock().unwrap().input_backend.clone() } {
+        InputBackend::Evdev => true,
+        InputBackend::Rdev => false,
+        InputBackend::Auto => {
+            std::env::var_os("WAYLAND_DISPLAY").is_some()
+                || std::env::var("XDG_SESSION_TYPE").map_or(false, |session| session.eq("wayland"))
+        }
+    }
+}
+
+/// 获取设备状态
+pub fn get_devices() -> Vec<InputDevice> {
+    DEVICES.lock().unwrap().values().cloned().collect()
+}
+
+/// 读取所有设备的事件并交给handler处理，不会返回
+pub fn start(handler: Arc<Mutex<Handler>>) {
+    let (sender, receiver) = mpsc::channel::<RawEvent>();
+    // 设备扫描线程，发现新设备时开启读取线程
+    thread::spawn(move || loop {
+        scan_devices(&sender);
+        thread::sleep(SCAN_INTERVAL);
+    });
+    let mut translator = Translator::new();
+    for raw in receiver {
+        if let Some(event) = translator.translate(&raw) {
+            handler.lock().unwrap().handle(event);
+        }
+    }
+}
+
+/// 扫描输入设备目录，打开还未读取的键盘和鼠标
+fn scan_devices(sender: &mpsc::Sender<RawEvent>) {
+    let entries = match fs::read_dir(INPUT_DIR) {
+        Ok(entries) => entries,
+        Err(error) => {
+            eprintln!("evdev error: cannot read {}: {}", INPUT_DIR, error);
+            return;
+        }
+    };
+    let selected = unsafe { CONFIG.lock().unwrap().input_devices.clone() };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_event = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| name.starts_with("event"));
+        if !is_event {
+            continue;
+        }
+        if let Some(device) = DEVICES.lock().unwrap().get(&path) {
+            // 已在读取或已确认不是键盘鼠标的设备不再打开
+            if device.opened || device.error.is_none() {
+                continue;
+            }
+        }
+        match Device::open(&path) {
+            Ok(device) => {
+                let name = device.name().map(|name| name.to_string());
+                let wanted = is_keyboard_or_mouse(&device)
+                    && (selected.is_empty()
+                        || selected.iter().any(|selected| {
+                            Some(selected) == name.as_ref() || Path::new(selected).eq(&path)
+                        }));
+                set_device(&path, name, wanted, None);
+                if wanted {
+                    let sender = sender.clone();
+                    let path = path.clone();
+                    thread::spawn(move || read_device(path, device, sender));
+                }
+            }
+            Err(error) => {
+                let message = if error.kind() == ErrorKind::PermissionDenied {
+                    "permission denied, add the user to the input group".to_string()
+                } else {
+                    error.to_string()
+                };
+                // 只在第一次出错时输出
+                let reported = DEVICES
+                    .lock()
+                    .unwrap()
+                    .get(&path)
+                    .map_or(false, |device| device.error.as_ref() == Some(&message));
+                if !reported {
+                    eprintln!("evdev error: {}: {}", path.display(), message);
+                }
+                set_device(&path, None, false, Some(message));
+            }
+        }
+    }
+}
+
+fn set_device(path: &Path, name: Option<String>, opened: bool, error: Option<String>) {
+    DEVICES.lock().unwrap().insert(
+        path.to_path_buf(),
+        InputDevice {
+            path: path.to_string_lossy().to_string(),
+            name,
+            opened,
+            error,
+        },
+    );
+}
+
+/// 判断设备是否为键盘或鼠标
+fn is_keyboard_or_mouse(device: &Device) -> bool {
+    let keyboard = device.supported_keys().map_or(false, |keys| {
+        keys.contains(EvdevKey::KEY_A) && keys.contains(EvdevKey::KEY_ENTER)
+    });
+    let mouse = device
+        .supported_relative_axes()
+        .map_or(false, |axes| axes.contains(RelativeAxisType::REL_X));
+    keyboard || mouse
+}
+
+/// 读取单个设备的事件，设备拔出时结束
+fn read_device(path: PathBuf, mut device: Device, sender: mpsc::Sender<RawEvent>) {
+    let name = device.name().map(|name| name.to_string());
+    loop {
+        let events = match device.fetch_events() {
+            Ok(events) => events,
+            Err(error) => {
+                // 设备被拔出时移除状态，下次扫描时重新打开
+                if error.raw_os_error() == Some(19) {
+                    DEVICES.lock().unwrap().remove(&path);
+                } else {
+                    set_device(&path, name, false, Some(error.to_string()));
+                }
+                return;
+            }
+        };
+        for event in events {
+            let raw = RawEvent {
+                time: event.timestamp(),
+                kind: event.event_type().0,
+                code: event.code(),
+                value: event.value(),
+            };
+            if let Err(_) = sender.send(raw) {
+                return;
+            }
+        }
+    }
+}
+
+/// 将evdev事件转换为rdev事件
+/// evdev只提供相对移动，鼠标位置从主屏幕中心开始累加，并限制在所有显示器的范围内
+pub struct Translator {
+    x: f64,
+    y: f64,
+    /// 是否有未发送的鼠标移动，收到同步事件时发送
+    moved: bool,
+}
+
+impl Translator {
+    pub fn new() -> Self {
+        let monitors = monitor::get_monitors();
+        let (x, y) = monitors
+            .iter()
+            .find(|monitor| monitor.is_primary)
+            .or(monitors.first())
+            .map(|monitor| {
+                (
+                    monitor.x as f64 + monitor.width as f64 / 2.0,
+                    monitor.y as f64 + monitor.height as f64 / 2.0,
+                )
+            })
+            .unwrap_or((960.0, 540.0));
+        Self { x, y, moved: false }
+    }
+
+    pub fn translate(&mut self, raw: &RawEvent) -> Option<Event> {
+        let event_type = match (raw.kind, raw.code) {
+            (EV_KEY, code) if is_button(code) => {
+                let button = button_from_code(code);
+                match raw.value {
+                    0 => EventType::ButtonRelease(button),
+                    1 => EventType::ButtonPress(button),
+                    _ => return None,
+                }
+            }
+            // 1为按下，2为按住时的重复
+            (EV_KEY, code) => match raw.value {
+                0 => EventType::KeyRelease(key_from_code(code)),
+                _ => EventType::KeyPress(key_from_code(code)),
+            },
+            (EV_REL, REL_X) => {
+                self.x += raw.value as f64;
+                self.moved = true;
+                return None;
+            }
+            (EV_REL, REL_Y) => {
+                self.y += raw.value as f64;
+                self.moved = true;
+                return None;
+            }
+            (EV_REL, REL_WHEEL) => EventType::Wheel {
+                delta_x: 0,
+                delta_y: raw.value as i64,
+            },
+            (EV_REL, REL_HWHEEL) => EventType::Wheel {
+                delta_x: raw.value as i64,
+                delta_y: 0,
+            },
+            (EV_SYN, _) if self.moved => {
+                self.moved = false;
+                self.clamp();
+                EventType::MouseMove {
+                    x: self.x,
+                    y: self.y,
+                }
+            }
+            _ => return None,
+        };
+        Some(Event {
+            time: raw.time,
+            // evdev没有键盘布局信息，无法得到输入的字符
+            name: None,
+            event_type,
+        })
+    }
+
+    /// 将鼠标位置限制在显示器范围内
+    fn clamp(&mut self) {
+        let monitors = monitor::get_monitors();
+        if monitors.is_empty() {
+            return;
+        }
+        let min_x = monitors.iter().map(|m| m.x as f64).fold(f64::MAX, f64::min);
+        let min_y = monitors.iter().map(|m| m.y as f64).fold(f64::MAX, f64::min);
+        let max_x = monitors
+            .iter()
+            .map(|m| (m.x + m.width as i32 - 1) as f64)
+            .fold(f64::MIN, f64::max);
+        let max_y = monitors
+            .iter()
+            .map(|m| (m.y + m.height as i32 - 1) as f64)
+            .fold(f64::MIN, f64::max);
+        self.x = self.x.clamp(min_x, max_x);
+        self.y = self.y.clamp(min_y, max_y);
+    }
+}
+
+fn is_button(code: u16) -> bool {
+    (BTN_LEFT..=BTN_TASK).contains(&code)
+}
+
+/// 鼠标按键，侧键与rdev在Windows下的编号一致
+/// BTN_SIDE、BTN_EXTRA、BTN_FORWARD、BTN_BACK、BTN_TASK依次为Unknown(1)~Unknown(5)
+fn button_from_code(code: u16) -> Button {
+    match code {
+        BTN_LEFT => Button::Left,
+        BTN_RIGHT => Button::Right,
+        BTN_MIDDLE => Button::Middle,
+        code => Button::Unknown((code - BTN_SIDE + 1) as u8),
+    }
+}
+
+/// 读取事件转储文件
+pub fn read_dump(path: &Path) -> Result<Vec<RawEvent>, DumpError> {
+    let text = fs::read_to_string(path).map_err(|_| DumpError::ReadFileError)?;
+    parse_dump(&text)
+}
+
+/// 解析事件转储，每行为一个RawEvent的JSON，或evtest的输出
+/// evtest中Event:开头以外的行(设备信息等)会被忽略
+pub fn parse_dump(text: &str) -> Result<Vec<RawEvent>, DumpError> {
+    let mut events = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.starts_with('{') {
+            let event = serde_json::from_str(line).map_err(|_| DumpError::InvalidDumpError)?;
+            events.push(event);
+        } else if let Some(rest) = line.strip_prefix("Event: time ") {
+            events.push(parse_evtest_line(rest).ok_or(DumpError::InvalidDumpError)?);
+        }
+    }
+    Ok(events)
+}
+
+/// 解析evtest的一行(去掉"Event: time "后)
+/// 如"1700000000.123456, type 1 (EV_KEY), code 30 (KEY_A), value 1"
+/// 或"1700000000.123456, -------------- SYN_REPORT ------------"
+fn parse_evtest_line(line: &str) -> Option<RawEvent> {
+    let mut parts = line.split(',').map(str::trim);
+    let (secs, micros) = parts.next()?.split_once('.')?;
+    let time = SystemTime::UNIX_EPOCH
+        + Duration::from_secs(secs.parse().ok()?)
+        + Duration::from_micros(micros.parse().ok()?);
+    if line.contains("SYN_REPORT") {
+        return Some(RawEvent {
+            time,
+            kind: EV_SYN,
+            code: 0,
+            value: 0,
+        });
+    }
+    let (mut kind, mut code, mut value) = (None, None, None);
+    for part in parts {
+        // 数值后面可能带有括号中的名称
+        let mut words = part.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("type"), Some(number)) => kind = number.parse().ok(),
+            (Some("code"), Some(number)) => code = number.parse().ok(),
+            (Some("value"), Some(number)) => value = Some(number),
+            _ => {}
+        }
+    }
+    // evtest以十六进制输出EV_MSC的扫描码
+    let kind = kind?;
+    let radix = if kind == EV_MSC { 16 } else { 10 };
+    Some(RawEvent {
+        time,
+        kind,
+        code: code?,
+        value: i32::from_str_radix(value?, radix).ok()?,
+    })
+}
+
+/// evdev按键码转换为rdev的Key，未知按键与rdev在X11下一致(按键码+8)
+#[rustfmt::skip]
+pub fn key_from_code(code: u16) -> Key {
+    match code {
+        1 => Key::Escape,
+        2 => Key::Num1, 3 => Key::Num2, 4 => Key::Num3, 5 => Key::Num4, 6 => Key::Num5,
+        7 => Key::Num6, 8 => Key::Num7, 9 => Key::Num8, 10 => Key::Num9, 11 => Key::Num0,
+        12 => Key::Minus, 13 => Key::Equal, 14 => Key::Backspace, 15 => Key::Tab,
+        16 => Key::KeyQ, 17 => Key::KeyW, 18 => Key::KeyE, 19 => Key::KeyR, 20 => Key::KeyT,
+        21 => Key::KeyY, 22 => Key::KeyU, 23 => Key::KeyI, 24 => Key::KeyO, 25 => Key::KeyP,
+        26 => Key::LeftBracket, 27 => Key::RightBracket, 28 => Key::Return, 29 => Key::ControlLeft,
+        30 => Key::KeyA, 31 => Key::KeyS, 32 => Key::KeyD, 33 => Key::KeyF, 34 => Key::KeyG,
+        35 => Key::KeyH, 36 => Key::KeyJ, 37 => Key::KeyK, 38 => Key::KeyL,
+        39 => Key::SemiColon, 40 => Key::Quote, 41 => Key::BackQuote, 42 => Key::ShiftLeft,
+        43 => Key::BackSlash,
+        44 => Key::KeyZ, 45 => Key::KeyX, 46 => Key::KeyC, 47 => Key::KeyV, 48 => Key::KeyB,
+        49 => Key::KeyN, 50 => Key::KeyM,
+        51 => Key::Comma, 52 => Key::Dot, 53 => Key::Slash, 54 => Key::ShiftRight,
+        55 => Key::KpMultiply, 56 => Key::Alt, 57 => Key::Space, 58 => Key::CapsLock,
+        59 => Key::F1, 60 => Key::F2, 61 => Key::F3, 62 => Key::F4, 63 => Key::F5,
+        64 => Key::F6, 65 => Key::F7, 66 => Key::F8, 67 => Key::F9, 68 => Key::F10,
+        69 => Key::NumLock, 70 => Key::ScrollLock,
+        71 => Key::Kp7, 72 => Key::Kp8, 73 => Key::Kp9, 74 => Key::KpMinus,
+        75 => Key::Kp4, 76 => Key::Kp5, 77 => Key::Kp6, 78 => Key::KpPlus,
+        79 => Key::Kp1, 80 => Key::Kp2, 81 => Key::Kp3, 82 => Key::Kp0, 83 => Key::KpDelete,
+        86 => Key::IntlBackslash, 87 => Key::F11, 88 => Key::F12,
+        96 => Key::KpReturn, 97 => Key::ControlRight, 98 => Key::KpDivide,
+        99 => Key::PrintScreen, 100 => Key::AltGr,
+        102 => Key::Home, 103 => Key::UpArrow, 104 => Key::PageUp, 105 => Key::LeftArrow,
+        106 => Key::RightArrow, 107 => Key::End, 108 => Key::DownArrow, 109 => Key::PageDown,
+        110 => Key::Insert, 111 => Key::Delete, 119 => Key::Pause,
+        125 => Key::MetaLeft, 126 => Key::MetaRight,
+        code => Key::Unknown(code as u32 + 8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 将转储中的事件逐个转换，返回转换得到的事件类型
+    fn translate(dump: &str) -> Vec<EventType> {
+        let mut translator = Translator::new();
+        parse_dump(dump)
+            .unwrap()
+            .iter()
+            .filter_map(|raw| translator.translate(raw))
+            .map(|event| event.event_type)
+            .collect()
+    }
+
+    #[test]
+    fn evtest_key_press_repeat_release() {
+        let dump = "Input driver version is 1.0.1
+Input device name: \"AT Translated Set 2 keyboard\"
+Testing ... (interrupt to exit)
+Event: time 1700000000.000100, type 4 (EV_MSC), code 4 (MSC_SCAN), value 1e
+Event: time 1700000000.000100, type 1 (EV_KEY), code 30 (KEY_A), value 1
+Event: time 1700000000.000100, -------------- SYN_REPORT ------------
+Event: time 1700000000.500100, type 1 (EV_KEY), code 30 (KEY_A), value 2
+Event: time 1700000000.500100, -------------- SYN_REPORT ------------
+Event: time 1700000000.600100, type 1 (EV_KEY), code 30 (KEY_A), value 0
+Event: time 1700000000.600100, -------------- SYN_REPORT ------------";
+        assert_eq!(
+            translate(dump),
+            vec![
+                EventType::KeyPress(Key::KeyA),
+                EventType::KeyPress(Key::KeyA),
+                EventType::KeyRelease(Key::KeyA),
+            ]
+        );
+    }
+
+    #[test]
+    fn evtest_time() {
+        let events = parse_dump(
+            "Event: time 1700000000.123456, type 1 (EV_KEY), code 29 (KEY_LEFTCTRL), value 1",
+        )
+        .unwrap();
+        assert_eq!(
+            events[0].time,
+            SystemTime::UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456)
+        );
+        assert_eq!(
+            (events[0].kind, events[0].code, events[0].value),
+            (EV_KEY, 29, 1)
+        );
+    }
+
+    #[test]
+    fn mouse_buttons() {
+        let dump = "Event: time 1700000000.000000, type 1 (EV_KEY), code 272 (BTN_LEFT), value 1
+Event: time 1700000000.010000, type 1 (EV_KEY), code 272 (BTN_LEFT), value 0
+Event: time 1700000000.020000, type 1 (EV_KEY), code 275 (BTN_SIDE), value 1
+Event: time 1700000000.030000, type 1 (EV_KEY), code 276 (BTN_EXTRA), value 1
+Event: time 1700000000.040000, type 1 (EV_KEY), code 277 (BTN_FORWARD), value 1
+Event: time 1700000000.050000, type 1 (EV_KEY), code 278 (BTN_BACK), value 1
+Event: time 1700000000.060000, type 1 (EV_KEY), code 279 (BTN_TASK), value 0";
+        assert_eq!(
+            translate(dump),
+            vec![
+                EventType::ButtonPress(Button::Left),
+                EventType::ButtonRelease(Button::Left),
+                EventType::ButtonPress(Button::Unknown(1)),
+                EventType::ButtonPress(Button::Unknown(2)),
+                EventType::ButtonPress(Button::Unknown(3)),
+                EventType::ButtonPress(Button::Unknown(4)),
+                EventType::ButtonRelease(Button::Unknown(5)),
+            ]
+        );
+    }
+
+    #[test]
+    fn relative_moves_are_sent_on_sync() {
+        let dump = "Event: time 1700000000.000000, type 2 (EV_REL), code 0 (REL_X), value 5
+Event: time 1700000000.000000, type 2 (EV_REL), code 1 (REL_Y), value -3
+Event: time 1700000000.000000, -------------- SYN_REPORT ------------
+Event: time 1700000000.008000, type 2 (EV_REL), code 0 (REL_X), value 5
+Event: time 1700000000.008000, -------------- SYN_REPORT ------------
+Event: time 1700000000.016000, -------------- SYN_REPORT ------------";
+        assert_eq!(
+            translate(dump),
+            vec![
+                EventType::MouseMove { x: 965.0, y: 537.0 },
+                EventType::MouseMove { x: 970.0, y: 537.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn wheel() {
+        let dump = "Event: time 1700000000.000000, type 2 (EV_REL), code 8 (REL_WHEEL), value -1
+Event: time 1700000000.000000, type 2 (EV_REL), code 6 (REL_HWHEEL), value 1";
+        assert_eq!(
+            translate(dump),
+            vec![
+                EventType::Wheel {
+                    delta_x: 0,
+                    delta_y: -1
+                },
+                EventType::Wheel {
+                    delta_x: 1,
+                    delta_y: 0
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn json_lines() {
+        let dump = r#"{"time":"1700000000000","kind":1,"code":100,"value":1}
+
+{"time":1700000000050,"kind":1,"code":100,"value":0}"#;
+        assert_eq!(
+            translate(dump),
+            vec![
+                EventType::KeyPress(Key::AltGr),
+                EventType::KeyRelease(Key::AltGr),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_key_matches_x11_keycode() {
+        let dump = r#"{"time":0,"kind":1,"code":183,"value":1}"#;
+        assert_eq!(
+            translate(dump),
+            vec![EventType::KeyPress(Key::Unknown(191))]
+        );
+    }
+
+    #[test]
+    fn invalid_dump() {
+        assert!(matches!(
+            parse_dump("Event: time 1700000000.000000, type 1 (EV_KEY), code 30 (KEY_A)"),
+            Err(DumpError::InvalidDumpError)
+        ));
+        assert!(matches!(
+            parse_dump(r#"{"kind":1}"#),
+            Err(DumpError::InvalidDumpError)
+        ));
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
-    fs::{self, ReadDir},
-    path::PathBuf,
+    fs::{self, File, ReadDir},
+    io::Write,
+    path::{Path, PathBuf},
 };
 
 #[derive(Debug)]
@@ -9,7 +10,8 @@ pub enum FileError {
     ReadDirError,
     FileTypeError,
     NotDirError,
-    NotFileError
+    NotFileError,
+    WriteError,
 }
 
 // 获取目录中的文件列表
@@ -24,3 +26,28 @@ pub fn get_dir_entries(path: Option<PathBuf>) -> Result<ReadDir, FileError> {
     }
     Ok(read_dir.unwrap())
 }
+
+// 原子写入文件：先写入同目录下的临时文件，再重命名覆盖目标文件
+pub fn write_file_atomic(path: &Path, contents: &[u8]) -> Result<(), FileError> {
+    let file_name = match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => return Err(FileError::PathResolveError),
+    };
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name));
+    // 写入临时文件并落盘
+    let file_res = File::create(&temp_path);
+    if let Err(_) = file_res {
+        return Err(FileError::WriteError);
+    }
+    let mut file = file_res.unwrap();
+    if let Err(_) = file.write_all(contents).and_then(|_| file.sync_all()) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(FileError::WriteError);
+    }
+    // 重命名为目标文件
+    if let Err(_) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(FileError::WriteError);
+    }
+    Ok(())
+}
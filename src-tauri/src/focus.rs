@@ -0,0 +1,316 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::Sender;
+
+use crate::{
+    inputs::Handler,
+    message::{Message, MessageData, MessageType},
+    CONFIG,
+};
+
+/// 检查焦点窗口的间隔
+const CHECK_INTERVAL: Duration = Duration::from_millis(250);
+/// 按下按键时焦点检查结果的有效时间，超过时在处理按键前重新检查
+pub const PRESS_CHECK_TTL: Duration = Duration::from_millis(50);
+
+/// 当前是否因焦点窗口暂停发送输入消息
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// 焦点窗口的获取方式和上次检查的时间，检查线程和处理按键时共用
+static CHECKER: Lazy<Mutex<FocusChecker>> = Lazy::new(|| {
+    Mutex::new(FocusChecker {
+        provider: create_provider(),
+        checked_at: None,
+    })
+});
+
+struct FocusChecker {
+    provider: Box<dyn FocusProvider>,
+    checked_at: Option<Instant>,
+}
+
+/// 焦点窗口的信息
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FocusedApp {
+    /// 窗口类名(X11的WM_CLASS)
+    pub class: String,
+    /// 窗口标题
+    pub title: String,
+}
+
+/// 暂停和恢复消息，不包含窗口信息，避免把窗口标题发送到网页
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FocusMessage {
+    pub paused: bool,
+}
+
+/// 获取焦点窗口，各平台分别实现
+pub trait FocusProvider: Send {
+    /// 获取不到时返回None，此时不暂停
+    fn focused(&mut self) -> Option<FocusedApp>;
+}
+
+/// 没有实现的平台，始终获取不到焦点窗口
+pub struct NoFocusProvider;
+
+impl FocusProvider for NoFocusProvider {
+    fn focused(&mut self) -> Option<FocusedApp> {
+        None
+    }
+}
+
+/// 应用过滤规则，按类名或标题匹配(不区分大小写，包含即匹配)
+/// 允许列表不为空时只在匹配允许列表的应用中发送，匹配禁止列表的应用中始终暂停
+#[derive(Debug, Clone, Default)]
+pub struct AppFilter {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl AppFilter {
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// 是否需要在该应用获得焦点时暂停
+    pub fn is_blocked(&self, app: &FocusedApp) -> bool {
+        if self.deny.iter().any(|pattern| matches_app(pattern, app)) {
+            return true;
+        }
+        !self.allow.is_empty() && !self.allow.iter().any(|pattern| matches_app(pattern, app))
+    }
+}
+
+fn matches_app(pattern: &str, app: &FocusedApp) -> bool {
+    let pattern = pattern.trim().to_lowercase();
+    !pattern.is_empty()
+        && (app.class.to_lowercase().contains(&pattern)
+            || app.title.to_lowercase().contains(&pattern))
+}
+
+/// 当前是否暂停发送输入消息
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// 获取当前平台的焦点窗口获取方式
+fn create_provider() -> Box<dyn FocusProvider> {
+    #[cfg(target_os = "linux")]
+    if let Some(provider) = x11_focus::X11FocusProvider::new() {
+        return Box::new(provider);
+    }
+    Box::new(NoFocusProvider)
+}
+
+/// 检查焦点窗口并更新暂停状态，距离上次检查不到max_age时使用上次的结果
+/// 返回暂停状态是否改变
+pub fn check(max_age: Duration) -> bool {
+    let mut checker = CHECKER.lock().unwrap();
+    if checker
+        .checked_at
+        .map_or(false, |checked_at| checked_at.elapsed() < max_age)
+    {
+        return false;
+    }
+    checker.checked_at = Some(Instant::now());
+    let filter = unsafe {
+        let config = CONFIG.lock().unwrap();
+        AppFilter {
+            allow: config.app_allow_list.clone(),
+            deny: config.app_deny_list.clone(),
+        }
+    };
+    let paused = !filter.is_empty()
+        && checker
+            .provider
+            .focused()
+            .map_or(false, |app| filter.is_blocked(&app));
+    PAUSED.swap(paused, Ordering::Relaxed) != paused
+}
+
+/// 定时检查焦点窗口，进入或离开需要暂停的应用时广播Paused/Resumed消息
+/// 暂停时松开所有按住的按键，暂停期间handler不处理输入
+/// 切换窗口后立即按下的按键由handler在处理前检查，见Handler::handle
+pub fn run(sender: Sender<Message>, handler: Arc<Mutex<Handler>>) {
+    loop {
+        thread::sleep(CHECK_INTERVAL);
+        if !check(Duration::ZERO) {
+            continue;
+        }
+        if is_paused() {
+            handler.lock().unwrap().release_all(SystemTime::now());
+        }
+        let _ = sender.send(get_message());
+    }
+}
+
+/// 当前的暂停状态消息
+pub fn get_message() -> Message {
+    let paused = is_paused();
+    Message {
+        r#type: if paused {
+            MessageType::Paused
+        } else {
+            MessageType::Resumed
+        },
+        data: MessageData::FocusMessage(FocusMessage { paused }),
+    }
+}
+
+/// 通过X11的_NET_ACTIVE_WINDOW获取焦点窗口，Wayland下只能获取XWayland窗口
+#[cfg(target_os = "linux")]
+mod x11_focus {
+    use std::{
+        ffi::CString,
+        mem,
+        os::raw::{c_int, c_long, c_uchar, c_ulong},
+        ptr, slice,
+    };
+
+    use x11::xlib;
+
+    use super::{FocusProvider, FocusedApp};
+
+    pub struct X11FocusProvider {
+        display: *mut xlib::Display,
+        active_window: xlib::Atom,
+        net_wm_name: xlib::Atom,
+        utf8_string: xlib::Atom,
+    }
+
+    // display只在检查线程中使用
+    unsafe impl Send for X11FocusProvider {}
+
+    /// 窗口已经关闭等错误时忽略，默认的错误处理会退出程序
+    /// 通过XESetError只处理这个连接上的错误，不影响其他连接的错误处理
+    unsafe extern "C" fn ignore_error(
+        _display: *mut xlib::Display,
+        _error: *mut xlib::xError,
+        _codes: *mut xlib::XExtCodes,
+        _ret_code: *mut c_int,
+    ) -> c_int {
+        // 返回非0表示错误已处理，不再交给XSetErrorHandler设置的全局处理
+        1
+    }
+
+    impl X11FocusProvider {
+        pub fn new() -> Option<Self> {
+            unsafe {
+                let display = xlib::XOpenDisplay(ptr::null());
+                if display.is_null() {
+                    return None;
+                }
+                let codes = xlib::XAddExtension(display);
+                if codes.is_null() {
+                    xlib::XCloseDisplay(display);
+                    return None;
+                }
+                xlib::XESetError(display, (*codes).extension, Some(ignore_error));
+                let intern = |name: &str| {
+                    let name = CString::new(name).unwrap();
+                    xlib::XInternAtom(display, name.as_ptr(), xlib::False)
+                };
+                Some(Self {
+                    display,
+                    active_window: intern("_NET_ACTIVE_WINDOW"),
+                    net_wm_name: intern("_NET_WM_NAME"),
+                    utf8_string: intern("UTF8_STRING"),
+                })
+            }
+        }
+
+        /// 读取窗口属性，32位格式的每一项为c_long大小
+        unsafe fn get_property(
+            &self,
+            window: xlib::Window,
+            property: xlib::Atom,
+            kind: xlib::Atom,
+        ) -> Option<Vec<u8>> {
+            let mut actual_type: xlib::Atom = 0;
+            let mut actual_format: c_int = 0;
+            let mut items: c_ulong = 0;
+            let mut bytes_after: c_ulong = 0;
+            let mut data: *mut c_uchar = ptr::null_mut();
+            let status = xlib::XGetWindowProperty(
+                self.display,
+                window,
+                property,
+                0,
+                1024,
+                xlib::False,
+                kind,
+                &mut actual_type,
+                &mut actual_format,
+                &mut items,
+                &mut bytes_after,
+                &mut data,
+            );
+            if status != xlib::Success as c_int || data.is_null() {
+                return None;
+            }
+            let item_size = match actual_format {
+                8 => 1,
+                16 => 2,
+                32 => mem::size_of::<c_long>(),
+                _ => 0,
+            };
+            let bytes = slice::from_raw_parts(data, items as usize * item_size).to_vec();
+            xlib::XFree(data as *mut _);
+            Some(bytes)
+        }
+
+        unsafe fn get_active_window(&self) -> Option<xlib::Window> {
+            let root = xlib::XDefaultRootWindow(self.display);
+            let bytes = self.get_property(root, self.active_window, xlib::XA_WINDOW)?;
+            let bytes: [u8; mem::size_of::<c_ulong>()] =
+                bytes.get(..mem::size_of::<c_ulong>())?.try_into().ok()?;
+            match c_ulong::from_ne_bytes(bytes) {
+                0 => None,
+                window => Some(window),
+            }
+        }
+    }
+
+    impl FocusProvider for X11FocusProvider {
+        fn focused(&mut self) -> Option<FocusedApp> {
+            unsafe {
+                let window = self.get_active_window()?;
+                // WM_CLASS为"instance\0class\0"
+                let class = self
+                    .get_property(window, xlib::XA_WM_CLASS, xlib::XA_STRING)
+                    .map(|bytes| {
+                        String::from_utf8_lossy(&bytes)
+                            .split('\0')
+                            .filter(|part| !part.is_empty())
+                            .collect::<Vec<&str>>()
+                            .join(" ")
+                    })
+                    .unwrap_or_default();
+                let title = self
+                    .get_property(window, self.net_wm_name, self.utf8_string)
+                    .filter(|bytes| !bytes.is_empty())
+                    .or_else(|| self.get_property(window, xlib::XA_WM_NAME, xlib::XA_STRING))
+                    .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+                    .unwrap_or_default();
+                Some(FocusedApp { class, title })
+            }
+        }
+    }
+
+    impl Drop for X11FocusProvider {
+        fn drop(&mut self) {
+            unsafe {
+                xlib::XCloseDisplay(self.display);
+            }
+        }
+    }
+}
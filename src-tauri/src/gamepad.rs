@@ -0,0 +1,527 @@
+use std::{
+    collections::HashMap,
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+use tokio::sync::broadcast::Sender;
+
+use crate::{
+    focus,
+    inputs::{self, InputInfo, InputMessage, InputSource},
+    message::{KeySender, Message},
+    mouse::MotionSender,
+    record::RecordedInput,
+    CONFIG,
+};
+
+/// 等待手柄事件的超时时间，也是发送被限流的摇杆值的检查间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+/// 手柄关闭时检查设置的间隔
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_millis(1000);
+/// 初始化手柄库失败(如Linux下没有libudev或权限不足)后重试的间隔
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 手柄摇杆和扳机的处理设置
+#[derive(Debug, Clone)]
+pub struct AxisOptions {
+    /// 死区，绝对值小于该值时视为0
+    pub deadzone: f64,
+    /// 每个轴的最大发送频率(Hz)，为0时不限制
+    pub rate_hz: u64,
+}
+
+/// 轴的发送状态
+struct AxisState {
+    /// 上次发送的值
+    sent: f64,
+    /// 上次发送的时间
+    sent_at: Instant,
+    /// 被限流还未发送的消息
+    pending: Option<InputMessage>,
+}
+
+/// 手柄轴过滤器，处理死区和限流
+/// 与具体的手柄库无关，可以直接输入轴的值进行测试
+pub struct AxisFilter {
+    axes: HashMap<(usize, String), AxisState>,
+}
+
+impl AxisFilter {
+    pub fn new() -> Self {
+        Self {
+            axes: HashMap::new(),
+        }
+    }
+
+    /// 轴的值变化时调用，返回需要立即发送的消息
+    pub fn on_axis(
+        &mut self,
+        gamepad: usize,
+        axis: &str,
+        value: f64,
+        now: Instant,
+        time: SystemTime,
+        options: &AxisOptions,
+    ) -> Option<InputMessage> {
+        let value = if value.abs() < options.deadzone {
+            0.0
+        } else {
+            value
+        };
+        let message = InputMessage {
+            source: InputSource::Gamepad,
+            info: InputInfo::GamepadAxis {
+                gamepad,
+                axis: axis.to_string(),
+                value,
+            },
+            time,
+            synthetic: false,
+        };
+        let state = match self.axes.get_mut(&(gamepad, axis.to_string())) {
+            Some(state) => state,
+            None => {
+                self.axes.insert(
+                    (gamepad, axis.to_string()),
+                    AxisState {
+                        sent: value,
+                        sent_at: now,
+                        pending: None,
+                    },
+                );
+                return Some(message);
+            }
+        };
+        if state.sent == value {
+            state.pending = None;
+            return None;
+        }
+        // 回到0时立即发送，避免摇杆松开后停在非0的位置
+        if value == 0.0 || now.duration_since(state.sent_at) >= min_interval(options.rate_hz) {
+            state.sent = value;
+            state.sent_at = now;
+            state.pending = None;
+            Some(message)
+        } else {
+            state.pending = Some(message);
+            None
+        }
+    }
+
+    /// 取出已经到达发送间隔的被限流的消息
+    pub fn flush(&mut self, now: Instant, options: &AxisOptions) -> Vec<InputMessage> {
+        let mut messages = Vec::new();
+        for state in self.axes.values_mut() {
+            if state.pending.is_none()
+                || now.duration_since(state.sent_at) < min_interval(options.rate_hz)
+            {
+                continue;
+            }
+            let message = state.pending.take().unwrap();
+            if let InputInfo::GamepadAxis { value, .. } = message.info {
+                state.sent = value;
+            }
+            state.sent_at = now;
+            messages.push(message);
+        }
+        messages
+    }
+
+    /// 手柄断开时清除该手柄的状态
+    pub fn remove_gamepad(&mut self, gamepad: usize) {
+        self.axes.retain(|(id, _), _| *id != gamepad);
+    }
+}
+
+/// 用录制的摇杆和扳机输入重新进行死区和限流处理，可以用于调整设置或重现问题
+/// 其他输入原样保留，被限流的值按POLL_INTERVAL的检查间隔发送
+pub fn replay_axes(inputs: &[RecordedInput], options: &AxisOptions) -> Vec<RecordedInput> {
+    let start = Instant::now();
+    let poll_ms = POLL_INTERVAL.as_millis() as u64;
+    let mut filter = AxisFilter::new();
+    let mut outputs = Vec::new();
+    let flush = |filter: &mut AxisFilter, offset_ms: u64, outputs: &mut Vec<RecordedInput>| {
+        let now = start + Duration::from_millis(offset_ms);
+        for message in filter.flush(now, options) {
+            outputs.push(RecordedInput { offset_ms, message });
+        }
+    };
+    let mut tick = 0;
+    for input in inputs {
+        while tick <= input.offset_ms {
+            flush(&mut filter, tick, &mut outputs);
+            tick += poll_ms;
+        }
+        let (gamepad, axis, value) = match &input.message.info {
+            InputInfo::GamepadAxis {
+                gamepad,
+                axis,
+                value,
+            } => (*gamepad, axis, *value),
+            _ => {
+                outputs.push(input.clone());
+                continue;
+            }
+        };
+        let now = start + Duration::from_millis(input.offset_ms);
+        if let Some(message) =
+            filter.on_axis(gamepad, axis, value, now, input.message.time, options)
+        {
+            outputs.push(RecordedInput {
+                offset_ms: input.offset_ms,
+                message,
+            });
+        }
+    }
+    // 发送最后还在等待的值
+    if let Some(last) = inputs.last() {
+        let interval = min_interval(options.rate_hz).as_millis() as u64;
+        let end = last.offset_ms + interval.max(poll_ms);
+        while tick <= end {
+            flush(&mut filter, tick, &mut outputs);
+            tick += poll_ms;
+        }
+    }
+    outputs
+}
+
+fn min_interval(rate_hz: u64) -> Duration {
+    if rate_hz == 0 {
+        Duration::ZERO
+    } else {
+        Duration::from_secs_f64(1.0 / rate_hz as f64)
+    }
+}
+
+/// 监听手柄输入，按键消息通过key_sender发送，摇杆和扳机消息通过motion_sender发送
+pub fn start(sender: Sender<Message>, key_sender: KeySender, motion_sender: MotionSender) {
+    let mut gilrs: Option<Gilrs> = None;
+    let mut filter = AxisFilter::new();
+    // 初始化失败的错误只输出一次
+    let mut init_error: Option<String> = None;
+    loop {
+        let (enable, options) = unsafe {
+            let config = CONFIG.lock().unwrap();
+            (
+                config.enable && config.gamepad_enable,
+                AxisOptions {
+                    deadzone: config.gamepad_deadzone,
+                    rate_hz: config.gamepad_axis_rate_hz,
+                },
+            )
+        };
+        if !enable && gilrs.is_none() {
+            thread::sleep(IDLE_CHECK_INTERVAL);
+            continue;
+        }
+        // 第一次开启时初始化，失败时隔一段时间重试
+        if gilrs.is_none() {
+            match Gilrs::new() {
+                Ok(new) => {
+                    if init_error.take().is_some() {
+                        eprintln!("gamepad initialized");
+                    }
+                    gilrs = Some(new);
+                }
+                Err(error) => {
+                    let error = format!("{:?}", error);
+                    if init_error.as_ref() != Some(&error) {
+                        eprintln!(
+                            "gamepad error: {}, retrying every {:?}",
+                            error, RETRY_INTERVAL
+                        );
+                        init_error = Some(error);
+                    }
+                    thread::sleep(RETRY_INTERVAL);
+                    continue;
+                }
+            }
+        }
+        let pads = gilrs.as_mut().unwrap();
+        let event = pads.next_event_blocking(Some(POLL_INTERVAL));
+        let send = |message: InputMessage| {
+            inputs::send_input(&sender, &key_sender, &motion_sender, message)
+        };
+        for message in filter.flush(Instant::now(), &options) {
+            send(message);
+        }
+        let event = match event {
+            Some(event) => event,
+            None => continue,
+        };
+        // 关闭或焦点在需要暂停的应用中时丢弃事件
+        if !enable || focus::is_paused() {
+            continue;
+        }
+        let id = usize::from(event.id);
+        let info = match event.event {
+            EventType::ButtonPressed(button, _) | EventType::ButtonReleased(button, _) => {
+                let pressing = matches!(event.event, EventType::ButtonPressed(..));
+                InputInfo::GamepadButton {
+                    gamepad: id,
+                    button: get_button_name(button).to_string(),
+                    pressing,
+                }
+            }
+            EventType::AxisChanged(axis, value, _) => {
+                if let Some(message) = filter.on_axis(
+                    id,
+                    get_axis_name(axis),
+                    value as f64,
+                    Instant::now(),
+                    event.time,
+                    &options,
+                ) {
+                    send(message);
+                }
+                continue;
+            }
+            // 模拟扳机的值作为轴发送
+            EventType::ButtonChanged(
+                button @ (Button::LeftTrigger2 | Button::RightTrigger2),
+                value,
+                _,
+            ) => {
+                let axis = if button == Button::LeftTrigger2 {
+                    "left_trigger"
+                } else {
+                    "right_trigger"
+                };
+                if let Some(message) =
+                    filter.on_axis(id, axis, value as f64, Instant::now(), event.time, &options)
+                {
+                    send(message);
+                }
+                continue;
+            }
+            EventType::Connected | EventType::Disconnected => {
+                let connected = matches!(event.event, EventType::Connected);
+                if !connected {
+                    filter.remove_gamepad(id);
+                }
+                InputInfo::GamepadConnection {
+                    gamepad: id,
+                    name: pads.gamepad(event.id).name().to_string(),
+                    connected,
+                }
+            }
+            _ => continue,
+        };
+        send(InputMessage {
+            source: InputSource::Gamepad,
+            info,
+            time: event.time,
+            synthetic: false,
+        });
+    }
+}
+
+/// 手柄按键名
+fn get_button_name(button: Button) -> &'static str {
+    match button {
+        Button::South => "south",
+        Button::East => "east",
+        Button::North => "north",
+        Button::West => "west",
+        Button::C => "c",
+        Button::Z => "z",
+        Button::LeftTrigger => "left_bumper",
+        Button::LeftTrigger2 => "left_trigger",
+        Button::RightTrigger => "right_bumper",
+        Button::RightTrigger2 => "right_trigger",
+        Button::Select => "select",
+        Button::Start => "start",
+        Button::Mode => "mode",
+        Button::LeftThumb => "left_thumb",
+        Button::RightThumb => "right_thumb",
+        Button::DPadUp => "dpad_up",
+        Button::DPadDown => "dpad_down",
+        Button::DPadLeft => "dpad_left",
+        Button::DPadRight => "dpad_right",
+        Button::Unknown => "unknown",
+    }
+}
+
+/// 手柄轴名
+fn get_axis_name(axis: Axis) -> &'static str {
+    match axis {
+        Axis::LeftStickX => "left_stick_x",
+        Axis::LeftStickY => "left_stick_y",
+        Axis::LeftZ => "left_z",
+        Axis::RightStickX => "right_stick_x",
+        Axis::RightStickY => "right_stick_y",
+        Axis::RightZ => "right_z",
+        Axis::DPadX => "dpad_x",
+        Axis::DPadY => "dpad_y",
+        Axis::Unknown => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: AxisOptions = AxisOptions {
+        deadzone: 0.1,
+        rate_hz: 50,
+    };
+
+    /// 模拟的轴输入，时间从固定的起点开始
+    struct Timeline {
+        filter: AxisFilter,
+        start: Instant,
+    }
+
+    impl Timeline {
+        fn new() -> Self {
+            Self {
+                filter: AxisFilter::new(),
+                start: Instant::now(),
+            }
+        }
+
+        fn axis(&mut self, ms: u64, axis: &str, value: f64) -> Option<f64> {
+            let now = self.start + Duration::from_millis(ms);
+            self.filter
+                .on_axis(0, axis, value, now, SystemTime::now(), &OPTIONS)
+                .map(value_of)
+        }
+
+        fn flush(&mut self, ms: u64) -> Vec<f64> {
+            let now = self.start + Duration::from_millis(ms);
+            self.filter
+                .flush(now, &OPTIONS)
+                .into_iter()
+                .map(value_of)
+                .collect()
+        }
+    }
+
+    fn value_of(message: InputMessage) -> f64 {
+        match message.info {
+            InputInfo::GamepadAxis { value, .. } => value,
+            info => panic!("not an axis: {:?}", info),
+        }
+    }
+
+    #[test]
+    fn deadzone() {
+        let mut timeline = Timeline::new();
+        assert_eq!(timeline.axis(0, "left_stick_x", 0.05), Some(0.0));
+        // 死区内的变化视为没有变化
+        assert_eq!(timeline.axis(100, "left_stick_x", -0.09), None);
+        assert_eq!(timeline.axis(200, "left_stick_x", 0.1), Some(0.1));
+        assert_eq!(timeline.axis(300, "left_stick_x", -0.5), Some(-0.5));
+    }
+
+    #[test]
+    fn rate_limit_keeps_latest_value() {
+        let mut timeline = Timeline::new();
+        assert_eq!(timeline.axis(0, "left_stick_x", 0.2), Some(0.2));
+        // 50Hz即20ms内只发送一次，期间只保留最新的值
+        assert_eq!(timeline.axis(5, "left_stick_x", 0.3), None);
+        assert_eq!(timeline.axis(10, "left_stick_x", 0.4), None);
+        assert_eq!(timeline.flush(15), Vec::<f64>::new());
+        assert_eq!(timeline.flush(20), vec![0.4]);
+        assert_eq!(timeline.flush(40), Vec::<f64>::new());
+        assert_eq!(timeline.axis(45, "left_stick_x", 0.5), Some(0.5));
+    }
+
+    #[test]
+    fn pending_value_dropped_when_back_to_sent() {
+        let mut timeline = Timeline::new();
+        timeline.axis(0, "left_stick_x", 0.2);
+        assert_eq!(timeline.axis(5, "left_stick_x", 0.3), None);
+        assert_eq!(timeline.axis(10, "left_stick_x", 0.2), None);
+        assert_eq!(timeline.flush(20), Vec::<f64>::new());
+    }
+
+    #[test]
+    fn release_to_zero_is_not_limited() {
+        let mut timeline = Timeline::new();
+        timeline.axis(0, "left_stick_x", 0.8);
+        assert_eq!(timeline.axis(5, "left_stick_x", 0.4), None);
+        // 回到死区内立即发送0，并丢弃被限流的值
+        assert_eq!(timeline.axis(8, "left_stick_x", 0.02), Some(0.0));
+        assert_eq!(timeline.flush(30), Vec::<f64>::new());
+    }
+
+    #[test]
+    fn axes_are_limited_separately() {
+        let mut timeline = Timeline::new();
+        assert_eq!(timeline.axis(0, "left_stick_x", 0.5), Some(0.5));
+        assert_eq!(timeline.axis(1, "left_stick_y", 0.5), Some(0.5));
+        assert_eq!(timeline.axis(2, "right_trigger", 1.0), Some(1.0));
+        assert_eq!(timeline.axis(3, "left_stick_y", 0.6), None);
+    }
+
+    #[test]
+    fn unlimited_rate() {
+        let options = AxisOptions {
+            deadzone: 0.0,
+            rate_hz: 0,
+        };
+        let mut filter = AxisFilter::new();
+        let start = Instant::now();
+        for (i, value) in [0.01, 0.02, 0.03].iter().enumerate() {
+            let message = filter.on_axis(0, "left_z", *value, start, SystemTime::now(), &options);
+            assert_eq!(message.map(value_of), Some(*value), "input {}", i);
+        }
+    }
+
+    #[test]
+    fn remove_gamepad_clears_state() {
+        let mut timeline = Timeline::new();
+        timeline.axis(0, "left_stick_x", 0.5);
+        timeline.axis(5, "left_stick_x", 0.6);
+        timeline.filter.remove_gamepad(0);
+        assert_eq!(timeline.flush(100), Vec::<f64>::new());
+        assert_eq!(timeline.axis(101, "left_stick_x", 0.5), Some(0.5));
+    }
+
+    /// 录制文件中的一行手柄轴输入
+    fn recorded_axis(offset_ms: u64, axis: &str, value: f64) -> String {
+        format!(
+            r#"{{"offset_ms":{},"message":{{"source":"gamepad","info":{{"gamepad":0,"axis":"{}","value":{}}},"time":"1700000000000"}}}}"#,
+            offset_ms, axis, value
+        )
+    }
+
+    #[test]
+    fn replay_recorded_axes() {
+        let mut lines: Vec<String> = Vec::new();
+        // 以1ms间隔录制的摇杆推到底再松开
+        for i in 0..=10 {
+            lines.push(recorded_axis(i, "left_stick_x", i as f64 / 10.0));
+        }
+        lines.push(r#"{"offset_ms":12,"message":{"source":"gamepad","info":{"gamepad":0,"button":"south","pressing":true},"time":"1700000000012"}}"#.to_string());
+        lines.push(recorded_axis(30, "left_stick_x", 0.05));
+        let inputs: Vec<RecordedInput> = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let outputs: Vec<(u64, Option<f64>)> = replay_axes(&inputs, &OPTIONS)
+            .into_iter()
+            .map(|output| {
+                let value = match output.message.info {
+                    InputInfo::GamepadAxis { value, .. } => Some(value),
+                    _ => None,
+                };
+                (output.offset_ms, value)
+            })
+            .collect();
+        assert_eq!(
+            outputs,
+            vec![
+                // 第一个值在死区内，之后20ms内的值被限流
+                (0, Some(0.0)),
+                (12, None),
+                // 被限流的最新值在20ms后的检查时发送
+                (20, Some(1.0)),
+                (30, Some(0.0)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,648 @@
+use std::{fs, path::Path};
+
+use resvg::{
+    tiny_skia,
+    usvg::{self, fontdb, PostProcessingSteps, TreeParsing, TreePostProc},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants,
+    file::write_file_atomic,
+    keys,
+    preset::{
+        check_preset_name, find_preset_dir, user_preset_dir, write_manifest, KeyRegion,
+        PresetError, PresetManifest,
+    },
+};
+
+/// 预设生成的键盘/鼠标布局模板
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutTemplate {
+    /// 全尺寸ANSI(104键)
+    FullSizeAnsi,
+    /// 无数字小键盘(87键)
+    Tkl,
+    /// 60%配列
+    SixtyPercent,
+    /// 全尺寸ISO(105键)
+    Iso,
+    /// 左手WASD区域
+    Wasd,
+    /// 数字小键盘
+    Numpad,
+    /// 三键鼠标
+    Mouse3Button,
+    /// 五键鼠标
+    Mouse5Button,
+}
+
+impl LayoutTemplate {
+    /// 通过命令行中的模板名获取模板
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "full_size_ansi" | "ansi" => Some(Self::FullSizeAnsi),
+            "tkl" => Some(Self::Tkl),
+            "sixty_percent" | "60" => Some(Self::SixtyPercent),
+            "iso" => Some(Self::Iso),
+            "wasd" => Some(Self::Wasd),
+            "numpad" => Some(Self::Numpad),
+            "mouse_3_button" | "mouse3" => Some(Self::Mouse3Button),
+            "mouse_5_button" | "mouse5" => Some(Self::Mouse5Button),
+            _ => None,
+        }
+    }
+}
+
+/// 预设生成选项
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenerateOptions {
+    /// 预设名
+    pub name: String,
+    /// 布局模板
+    pub template: LayoutTemplate,
+    /// 1u按键的边长(px)
+    #[serde(default = "default_key_size")]
+    pub key_size: u32,
+    /// 按键间隔(px)
+    #[serde(default = "default_gap")]
+    pub gap: u32,
+    /// 字体
+    #[serde(default = "default_font_family")]
+    pub font_family: String,
+    /// 字号(px)，为空时根据按键大小计算
+    #[serde(default)]
+    pub font_size: Option<u32>,
+    /// 背景颜色
+    #[serde(default = "default_background_color")]
+    pub background_color: String,
+    /// 按键颜色
+    #[serde(default = "default_key_color")]
+    pub key_color: String,
+    /// 按键边框颜色
+    #[serde(default = "default_border_color")]
+    pub border_color: String,
+    /// 文字颜色
+    #[serde(default = "default_text_color")]
+    pub text_color: String,
+}
+
+fn default_key_size() -> u32 {
+    54
+}
+
+fn default_gap() -> u32 {
+    6
+}
+
+fn default_font_family() -> String {
+    "sans-serif".to_string()
+}
+
+fn default_background_color() -> String {
+    "#1e1e1e".to_string()
+}
+
+fn default_key_color() -> String {
+    "#2d2d2d".to_string()
+}
+
+fn default_border_color() -> String {
+    "#5a5a5a".to_string()
+}
+
+fn default_text_color() -> String {
+    "#f0f0f0".to_string()
+}
+
+impl GenerateOptions {
+    /// 使用默认样式
+    pub fn new(name: String, template: LayoutTemplate) -> Self {
+        Self {
+            name,
+            template,
+            key_size: default_key_size(),
+            gap: default_gap(),
+            font_family: default_font_family(),
+            font_size: None,
+            background_color: default_background_color(),
+            key_color: default_key_color(),
+            border_color: default_border_color(),
+            text_color: default_text_color(),
+        }
+    }
+}
+
+/// 模板中的单个按键，单位为u(1u为一个标准按键宽度)
+struct TemplateKey {
+    name: &'static str,
+    label: &'static str,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    /// 向下延伸的部分(x, 宽, 高)，从按键顶部开始且与按键右侧对齐，如ISO回车键
+    stem: Option<(f64, f64, f64)>,
+}
+
+/// 模板中的装饰图形(如鼠标外壳)，不对应按键
+struct TemplateShape {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    radius: f64,
+}
+
+/// 生成预设并写入用户预设目录
+#[tauri::command]
+pub fn generate_preset(options: GenerateOptions) -> Result<PresetManifest, PresetError> {
+    check_preset_name(&options.name)?;
+    if find_preset_dir(&options.name).is_some() {
+        return Err(PresetError::AlreadyExistsError);
+    }
+    generate_to_dir(&options, &user_preset_dir(&options.name))
+}
+
+/// 生成预设的图片(png和svg)和配置并写入目录
+pub fn generate_to_dir(
+    options: &GenerateOptions,
+    dir: &Path,
+) -> Result<PresetManifest, PresetError> {
+    if options.key_size == 0 {
+        return Err(PresetError::InvalidManifestError);
+    }
+    let (manifest, svg) = render_svg(options);
+    let png = render_png(&svg, manifest.width, manifest.height)?;
+    fs::create_dir_all(dir).map_err(|_| PresetError::WriteFilesError)?;
+    let res = write_file_atomic(&dir.join(constants::PRESET_IMAGE_FILE_NAME), &png)
+        .and_then(|_| write_file_atomic(&dir.join(constants::PRESET_SVG_FILE_NAME), svg.as_bytes()))
+        .map_err(|_| PresetError::WriteFilesError)
+        .and_then(|_| write_manifest(dir, &manifest));
+    // 写入失败则不留下不完整的预设
+    if let Err(error) = res {
+        let _ = fs::remove_dir_all(dir);
+        return Err(error);
+    }
+    Ok(manifest)
+}
+
+/// 根据模板生成按键区域和svg图片
+fn render_svg(options: &GenerateOptions) -> (PresetManifest, String) {
+    let (template_keys, shapes) = get_template(options.template);
+    let unit = (options.key_size + options.gap) as f64;
+    let padding = options.gap as f64 * 2.0;
+    let font_size = options
+        .font_size
+        .unwrap_or((options.key_size as f64 / 3.5).round() as u32);
+    let radius = options.key_size as f64 / 9.0;
+    // 将u转换为像素，按键之间留出间隔
+    let to_px = |value: f64| (padding + value * unit).round() as u32;
+    let to_len = |value: f64| (value * unit - options.gap as f64).round().max(1.0) as u32;
+
+    let mut regions = Vec::new();
+    let mut stems = Vec::new();
+    let mut width = 0;
+    let mut height = 0;
+    for key in template_keys.iter() {
+        let region = KeyRegion {
+            name: key.name.to_string(),
+            x: to_px(key.x),
+            y: to_px(key.y),
+            width: to_len(key.width),
+            height: to_len(key.height),
+            style: None,
+        };
+        // 延伸部分作为同名的另一个区域
+        let stem = key.stem.map(|(x, stem_width, stem_height)| KeyRegion {
+            name: key.name.to_string(),
+            x: to_px(x),
+            y: to_px(key.y),
+            width: to_len(stem_width),
+            height: to_len(stem_height),
+            style: None,
+        });
+        for region in std::iter::once(&region).chain(stem.iter()) {
+            width = width.max(region.x + region.width);
+            height = height.max(region.y + region.height);
+        }
+        regions.push(region);
+        stems.push(stem);
+    }
+    for shape in shapes.iter() {
+        width = width.max(to_px(shape.x) + to_len(shape.width));
+        height = height.max(to_px(shape.y) + to_len(shape.height));
+    }
+    let width = width + padding as u32;
+    let height = height + padding as u32;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n"
+    );
+    svg.push_str(&format!(
+        "  <rect x=\"0\" y=\"0\" width=\"{width}\" height=\"{height}\" fill=\"{}\"/>\n",
+        escape_xml(&options.background_color)
+    ));
+    for shape in shapes.iter() {
+        svg.push_str(&format!(
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>\n",
+            to_px(shape.x),
+            to_px(shape.y),
+            to_len(shape.width),
+            to_len(shape.height),
+            shape.radius * unit,
+            escape_xml(&options.border_color)
+        ));
+    }
+    for ((key, region), stem) in template_keys.iter().zip(regions.iter()).zip(stems.iter()) {
+        match stem {
+            // 按键和延伸部分合并为L形
+            Some(stem) => svg.push_str(&format!(
+                "  <path d=\"M {} {} H {} V {} H {} V {} H {} Z\" fill=\"{}\" stroke=\"{}\" stroke-width=\"1\" stroke-linejoin=\"round\"/>\n",
+                region.x,
+                region.y,
+                region.x + region.width,
+                stem.y + stem.height,
+                stem.x,
+                region.y + region.height,
+                region.x,
+                escape_xml(&options.key_color),
+                escape_xml(&options.border_color)
+            )),
+            None => svg.push_str(&format!(
+                "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{radius}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"1\"/>\n",
+                region.x,
+                region.y,
+                region.width,
+                region.height,
+                escape_xml(&options.key_color),
+                escape_xml(&options.border_color)
+            )),
+        }
+        if key.label.is_empty() {
+            continue;
+        }
+        svg.push_str(&format!(
+            "  <text x=\"{}\" y=\"{}\" font-family=\"{}\" font-size=\"{font_size}\" fill=\"{}\" text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+            region.x as f64 + region.width as f64 / 2.0,
+            region.y as f64 + region.height as f64 / 2.0,
+            escape_xml(&options.font_family),
+            escape_xml(&options.text_color),
+            escape_xml(key.label)
+        ));
+    }
+    svg.push_str("</svg>\n");
+
+    let manifest = PresetManifest {
+        width,
+        height,
+        keys: regions
+            .into_iter()
+            .zip(stems)
+            .flat_map(|(region, stem)| std::iter::once(region).chain(stem))
+            .collect(),
+        ..Default::default()
+    };
+    (manifest, svg)
+}
+
+/// 将svg渲染为png
+fn render_png(svg: &str, width: u32, height: u32) -> Result<Vec<u8>, PresetError> {
+    let mut tree = usvg::Tree::from_str(svg, &usvg::Options::default())
+        .map_err(|_| PresetError::InvalidImageError)?;
+    // 文字需要先通过系统字体转换为路径
+    let mut fontdb = fontdb::Database::new();
+    fontdb.load_system_fonts();
+    tree.postprocess(PostProcessingSteps::default(), &fontdb);
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or(PresetError::InvalidImageError)?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    pixmap
+        .encode_png()
+        .map_err(|_| PresetError::InvalidImageError)
+}
+
+/// 转义svg文本中的特殊字符
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// 将一行按键依次排列，按键名为空时作为空位
+fn row(
+    list: &mut Vec<TemplateKey>,
+    x: f64,
+    y: f64,
+    row_keys: &[(&'static str, &'static str, f64)],
+) {
+    let mut x = x;
+    for (name, label, width) in row_keys.iter() {
+        if !name.is_empty() {
+            list.push(TemplateKey {
+                name,
+                label,
+                x,
+                y,
+                width: *width,
+                height: 1.0,
+                stem: None,
+            });
+        }
+        x += width;
+    }
+}
+
+/// 单个按键
+fn key(
+    name: &'static str,
+    label: &'static str,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+) -> TemplateKey {
+    TemplateKey {
+        name,
+        label,
+        x,
+        y,
+        width,
+        height,
+        stem: None,
+    }
+}
+
+/// 获取模板的按键和装饰图形
+#[rustfmt::skip]
+fn get_template(template: LayoutTemplate) -> (Vec<TemplateKey>, Vec<TemplateShape>) {
+    let mut list = Vec::new();
+    let mut shapes = Vec::new();
+    match template {
+        LayoutTemplate::FullSizeAnsi => {
+            function_rows(&mut list);
+            main_block_ansi(&mut list, 1.5, false);
+            navigation_block(&mut list, 15.25, 0.0);
+            numpad_block(&mut list, 18.5, 1.5);
+        }
+        LayoutTemplate::Tkl => {
+            function_rows(&mut list);
+            main_block_ansi(&mut list, 1.5, false);
+            navigation_block(&mut list, 15.25, 0.0);
+        }
+        LayoutTemplate::SixtyPercent => {
+            main_block_ansi(&mut list, 0.0, true);
+        }
+        LayoutTemplate::Iso => {
+            function_rows(&mut list);
+            main_block_iso(&mut list, 1.5);
+            navigation_block(&mut list, 15.25, 0.0);
+            numpad_block(&mut list, 18.5, 1.5);
+        }
+        LayoutTemplate::Wasd => {
+            row(&mut list, 0.0, 0.0, &[(keys::ESCAPE, "Esc", 1.0), (keys::NUM_1, "1", 1.0), (keys::NUM_2, "2", 1.0), (keys::NUM_3, "3", 1.0), (keys::NUM_4, "4", 1.0), (keys::NUM_5, "5", 1.0)]);
+            row(&mut list, 0.0, 1.0, &[(keys::TAB, "Tab", 1.5), (keys::KEY_Q, "Q", 1.0), (keys::KEY_W, "W", 1.0), (keys::KEY_E, "E", 1.0), (keys::KEY_R, "R", 1.0), (keys::KEY_T, "T", 1.0)]);
+            row(&mut list, 0.0, 2.0, &[(keys::CAPSLOCK, "Caps", 1.75), (keys::KEY_A, "A", 1.0), (keys::KEY_S, "S", 1.0), (keys::KEY_D, "D", 1.0), (keys::KEY_F, "F", 1.0), (keys::KEY_G, "G", 1.0)]);
+            row(&mut list, 0.0, 3.0, &[(keys::SHIFT_LEFT, "Shift", 2.25), (keys::KEY_Z, "Z", 1.0), (keys::KEY_X, "X", 1.0), (keys::KEY_C, "C", 1.0), (keys::KEY_V, "V", 1.0), (keys::KEY_B, "B", 1.0)]);
+            row(&mut list, 0.0, 4.0, &[(keys::CONTROL_LEFT, "Ctrl", 1.25), (keys::META_LEFT, "Win", 1.25), (keys::ALT_LEFT, "Alt", 1.25), (keys::SPACE, "Space", 3.5)]);
+        }
+        LayoutTemplate::Numpad => {
+            numpad_block(&mut list, 0.0, 0.0);
+        }
+        LayoutTemplate::Mouse3Button | LayoutTemplate::Mouse5Button => {
+            shapes.push(TemplateShape {
+                x: 0.5,
+                y: 0.0,
+                width: 3.0,
+                height: 5.0,
+                radius: 1.2,
+            });
+            list.push(key(keys::MOUSE_LEFT, "L", 0.5, 0.0, 1.25, 2.25));
+            list.push(key(keys::MOUSE_MIDDLE, "M", 1.75, 0.5, 0.5, 1.25));
+            list.push(key(keys::MOUSE_RIGHT, "R", 2.25, 0.0, 1.25, 2.25));
+            if let LayoutTemplate::Mouse5Button = template {
+                // 侧键在鼠标外壳左侧
+                list.push(key(keys::MOUSE_5, "5", 0.0, 2.5, 0.5, 0.75));
+                list.push(key(keys::MOUSE_4, "4", 0.0, 3.25, 0.5, 0.75));
+            }
+        }
+    }
+    (list, shapes)
+}
+
+/// F区
+#[rustfmt::skip]
+fn function_rows(list: &mut Vec<TemplateKey>) {
+    row(list, 0.0, 0.0, &[
+        (keys::ESCAPE, "Esc", 1.0), ("", "", 1.0),
+        (keys::F1, "F1", 1.0), (keys::F2, "F2", 1.0), (keys::F3, "F3", 1.0), (keys::F4, "F4", 1.0), ("", "", 0.5),
+        (keys::F5, "F5", 1.0), (keys::F6, "F6", 1.0), (keys::F7, "F7", 1.0), (keys::F8, "F8", 1.0), ("", "", 0.5),
+        (keys::F9, "F9", 1.0), (keys::F10, "F10", 1.0), (keys::F11, "F11", 1.0), (keys::F12, "F12", 1.0),
+    ]);
+}
+
+/// 数字行，60%配列中最左侧为Esc
+#[rustfmt::skip]
+fn number_row(list: &mut Vec<TemplateKey>, y: f64, escape: bool) {
+    let first = if escape {
+        (keys::ESCAPE, "Esc", 1.0)
+    } else {
+        (keys::BACK_QUOTE, "`", 1.0)
+    };
+    row(list, 0.0, y, &[
+        first,
+        (keys::NUM_1, "1", 1.0), (keys::NUM_2, "2", 1.0), (keys::NUM_3, "3", 1.0), (keys::NUM_4, "4", 1.0),
+        (keys::NUM_5, "5", 1.0), (keys::NUM_6, "6", 1.0), (keys::NUM_7, "7", 1.0), (keys::NUM_8, "8", 1.0),
+        (keys::NUM_9, "9", 1.0), (keys::NUM_0, "0", 1.0), (keys::MINUS, "-", 1.0), (keys::EQUAL, "=", 1.0),
+        (keys::BACKSPACE, "Backspace", 2.0),
+    ]);
+}
+
+/// 空格行
+#[rustfmt::skip]
+fn bottom_row(list: &mut Vec<TemplateKey>, y: f64) {
+    row(list, 0.0, y, &[
+        (keys::CONTROL_LEFT, "Ctrl", 1.25), (keys::META_LEFT, "Win", 1.25), (keys::ALT_LEFT, "Alt", 1.25),
+        (keys::SPACE, "", 6.25),
+        (keys::ALT_RIGHT, "Alt", 1.25), (keys::META_RIGHT, "Win", 1.25), (keys::FUNCTION, "Fn", 1.25),
+        (keys::CONTROL_RIGHT, "Ctrl", 1.25),
+    ]);
+}
+
+/// ANSI主键区
+#[rustfmt::skip]
+fn main_block_ansi(list: &mut Vec<TemplateKey>, y: f64, escape: bool) {
+    number_row(list, y, escape);
+    row(list, 0.0, y + 1.0, &[
+        (keys::TAB, "Tab", 1.5),
+        (keys::KEY_Q, "Q", 1.0), (keys::KEY_W, "W", 1.0), (keys::KEY_E, "E", 1.0), (keys::KEY_R, "R", 1.0),
+        (keys::KEY_T, "T", 1.0), (keys::KEY_Y, "Y", 1.0), (keys::KEY_U, "U", 1.0), (keys::KEY_I, "I", 1.0),
+        (keys::KEY_O, "O", 1.0), (keys::KEY_P, "P", 1.0), (keys::LEFT_BRACKET, "[", 1.0),
+        (keys::RIGHT_BRACKET, "]", 1.0), (keys::BACKSLASH, "\\", 1.5),
+    ]);
+    row(list, 0.0, y + 2.0, &[
+        (keys::CAPSLOCK, "Caps", 1.75),
+        (keys::KEY_A, "A", 1.0), (keys::KEY_S, "S", 1.0), (keys::KEY_D, "D", 1.0), (keys::KEY_F, "F", 1.0),
+        (keys::KEY_G, "G", 1.0), (keys::KEY_H, "H", 1.0), (keys::KEY_J, "J", 1.0), (keys::KEY_K, "K", 1.0),
+        (keys::KEY_L, "L", 1.0), (keys::SEMICOLON, ";", 1.0), (keys::QUOTE, "'", 1.0),
+        (keys::RETURN, "Enter", 2.25),
+    ]);
+    row(list, 0.0, y + 3.0, &[
+        (keys::SHIFT_LEFT, "Shift", 2.25),
+        (keys::KEY_Z, "Z", 1.0), (keys::KEY_X, "X", 1.0), (keys::KEY_C, "C", 1.0), (keys::KEY_V, "V", 1.0),
+        (keys::KEY_B, "B", 1.0), (keys::KEY_N, "N", 1.0), (keys::KEY_M, "M", 1.0), (keys::COMMA, ",", 1.0),
+        (keys::DOT, ".", 1.0), (keys::SLASH, "/", 1.0),
+        (keys::SHIFT_RIGHT, "Shift", 2.75),
+    ]);
+    bottom_row(list, y + 4.0);
+}
+
+/// ISO主键区，回车键为占两行的L形
+#[rustfmt::skip]
+fn main_block_iso(list: &mut Vec<TemplateKey>, y: f64) {
+    number_row(list, y, false);
+    row(list, 0.0, y + 1.0, &[
+        (keys::TAB, "Tab", 1.5),
+        (keys::KEY_Q, "Q", 1.0), (keys::KEY_W, "W", 1.0), (keys::KEY_E, "E", 1.0), (keys::KEY_R, "R", 1.0),
+        (keys::KEY_T, "T", 1.0), (keys::KEY_Y, "Y", 1.0), (keys::KEY_U, "U", 1.0), (keys::KEY_I, "I", 1.0),
+        (keys::KEY_O, "O", 1.0), (keys::KEY_P, "P", 1.0), (keys::LEFT_BRACKET, "[", 1.0),
+        (keys::RIGHT_BRACKET, "]", 1.0),
+    ]);
+    list.push(TemplateKey {
+        stem: Some((13.75, 1.25, 2.0)),
+        ..key(keys::RETURN, "Enter", 13.5, y + 1.0, 1.5, 1.0)
+    });
+    row(list, 0.0, y + 2.0, &[
+        (keys::CAPSLOCK, "Caps", 1.75),
+        (keys::KEY_A, "A", 1.0), (keys::KEY_S, "S", 1.0), (keys::KEY_D, "D", 1.0), (keys::KEY_F, "F", 1.0),
+        (keys::KEY_G, "G", 1.0), (keys::KEY_H, "H", 1.0), (keys::KEY_J, "J", 1.0), (keys::KEY_K, "K", 1.0),
+        (keys::KEY_L, "L", 1.0), (keys::SEMICOLON, ";", 1.0), (keys::QUOTE, "'", 1.0),
+        (keys::BACKSLASH, "#", 1.0),
+    ]);
+    row(list, 0.0, y + 3.0, &[
+        (keys::SHIFT_LEFT, "Shift", 1.25), (keys::INTLBACKSLASH, "\\", 1.0),
+        (keys::KEY_Z, "Z", 1.0), (keys::KEY_X, "X", 1.0), (keys::KEY_C, "C", 1.0), (keys::KEY_V, "V", 1.0),
+        (keys::KEY_B, "B", 1.0), (keys::KEY_N, "N", 1.0), (keys::KEY_M, "M", 1.0), (keys::COMMA, ",", 1.0),
+        (keys::DOT, ".", 1.0), (keys::SLASH, "/", 1.0),
+        (keys::SHIFT_RIGHT, "Shift", 2.75),
+    ]);
+    bottom_row(list, y + 4.0);
+}
+
+/// 功能键和方向键区
+#[rustfmt::skip]
+fn navigation_block(list: &mut Vec<TemplateKey>, x: f64, y: f64) {
+    row(list, x, y, &[(keys::PRINT_SCREEN, "PrtSc", 1.0), (keys::SCROLL_LOCK, "ScrLk", 1.0), (keys::PAUSE, "Pause", 1.0)]);
+    row(list, x, y + 1.5, &[(keys::INSERT, "Ins", 1.0), (keys::HOME, "Home", 1.0), (keys::PAGEUP, "PgUp", 1.0)]);
+    row(list, x, y + 2.5, &[(keys::DELETE, "Del", 1.0), (keys::END, "End", 1.0), (keys::PAGEDOWN, "PgDn", 1.0)]);
+    row(list, x + 1.0, y + 4.5, &[(keys::UP_ARROW, "↑", 1.0)]);
+    row(list, x, y + 5.5, &[(keys::LEFT_ARROW, "←", 1.0), (keys::DOWNARROW, "↓", 1.0), (keys::RIGHT_ARROW, "→", 1.0)]);
+}
+
+/// 数字小键盘区
+#[rustfmt::skip]
+fn numpad_block(list: &mut Vec<TemplateKey>, x: f64, y: f64) {
+    row(list, x, y, &[(keys::NUM_LOCK, "Num", 1.0), (keys::KP_DIVIDE, "/", 1.0), (keys::KP_MULTIPLY, "*", 1.0), (keys::KP_MINUS, "-", 1.0)]);
+    row(list, x, y + 1.0, &[(keys::KP_7, "7", 1.0), (keys::KP_8, "8", 1.0), (keys::KP_9, "9", 1.0)]);
+    list.push(key(keys::KP_PLUS, "+", x + 3.0, y + 1.0, 1.0, 2.0));
+    row(list, x, y + 2.0, &[(keys::KP_4, "4", 1.0), (keys::KP_5, "5", 1.0), (keys::KP_6, "6", 1.0)]);
+    row(list, x, y + 3.0, &[(keys::KP_1, "1", 1.0), (keys::KP_2, "2", 1.0), (keys::KP_3, "3", 1.0)]);
+    list.push(key(keys::KP_RETURN, "Enter", x + 3.0, y + 3.0, 1.0, 2.0));
+    row(list, x, y + 4.0, &[(keys::KP_0, "0", 2.0), (keys::KP_DELETE, ".", 1.0)]);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rdev::{Button, Key};
+
+    use super::*;
+    use crate::inputs::{get_key_name, get_mouse_button_name};
+
+    /// rdev中有名称的按键
+    #[rustfmt::skip]
+    const KEYS: &[Key] = &[
+        Key::Alt, Key::AltGr, Key::Backspace, Key::CapsLock, Key::ControlLeft, Key::ControlRight,
+        Key::Delete, Key::DownArrow, Key::End, Key::Escape, Key::F1, Key::F10, Key::F11, Key::F12,
+        Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::Home,
+        Key::LeftArrow, Key::MetaLeft, Key::MetaRight, Key::PageDown, Key::PageUp, Key::Return,
+        Key::RightArrow, Key::ShiftLeft, Key::ShiftRight, Key::Space, Key::Tab, Key::UpArrow,
+        Key::PrintScreen, Key::ScrollLock, Key::Pause, Key::NumLock, Key::BackQuote, Key::Num1,
+        Key::Num2, Key::Num3, Key::Num4, Key::Num5, Key::Num6, Key::Num7, Key::Num8, Key::Num9,
+        Key::Num0, Key::Minus, Key::Equal, Key::KeyQ, Key::KeyW, Key::KeyE, Key::KeyR, Key::KeyT,
+        Key::KeyY, Key::KeyU, Key::KeyI, Key::KeyO, Key::KeyP, Key::LeftBracket, Key::RightBracket,
+        Key::KeyA, Key::KeyS, Key::KeyD, Key::KeyF, Key::KeyG, Key::KeyH, Key::KeyJ, Key::KeyK,
+        Key::KeyL, Key::SemiColon, Key::Quote, Key::BackSlash, Key::IntlBackslash, Key::KeyZ,
+        Key::KeyX, Key::KeyC, Key::KeyV, Key::KeyB, Key::KeyN, Key::KeyM, Key::Comma, Key::Dot,
+        Key::Slash, Key::Insert, Key::KpReturn, Key::KpMinus, Key::KpPlus, Key::KpMultiply,
+        Key::KpDivide, Key::Kp0, Key::Kp1, Key::Kp2, Key::Kp3, Key::Kp4, Key::Kp5, Key::Kp6,
+        Key::Kp7, Key::Kp8, Key::Kp9, Key::KpDelete, Key::Function,
+    ];
+
+    #[test]
+    fn region_names_match_input_names() {
+        let mut names: HashSet<&str> = KEYS
+            .iter()
+            .filter_map(|key| get_key_name(*key).ok())
+            .collect();
+        for button in [
+            Button::Left,
+            Button::Right,
+            Button::Middle,
+            Button::Unknown(1),
+            Button::Unknown(2),
+        ] {
+            names.extend(get_mouse_button_name(button).ok());
+        }
+        let templates = [
+            LayoutTemplate::FullSizeAnsi,
+            LayoutTemplate::Tkl,
+            LayoutTemplate::SixtyPercent,
+            LayoutTemplate::Iso,
+            LayoutTemplate::Wasd,
+            LayoutTemplate::Numpad,
+            LayoutTemplate::Mouse3Button,
+            LayoutTemplate::Mouse5Button,
+        ];
+        for template in templates {
+            let (template_keys, _) = get_template(template);
+            for key in template_keys {
+                assert!(names.contains(key.name), "{:?}: {}", template, key.name);
+            }
+        }
+    }
+
+    #[test]
+    fn iso_enter_is_l_shaped() {
+        let options = GenerateOptions::new("iso".to_string(), LayoutTemplate::Iso);
+        let (manifest, svg) = render_svg(&options);
+        let enter: Vec<(u32, u32, u32, u32)> = manifest
+            .keys
+            .iter()
+            .filter(|region| region.name.eq(keys::RETURN))
+            .map(|region| (region.x, region.y, region.width, region.height))
+            .collect();
+        let find = |name: &str| {
+            manifest
+                .keys
+                .iter()
+                .find(|region| region.name.eq(name))
+                .unwrap()
+        };
+        let right_bracket = find(keys::RIGHT_BRACKET);
+        let hash = find(keys::BACKSLASH);
+        // 上半部分紧接]键，下半部分紧接#键，右侧对齐
+        assert_eq!(enter.len(), 2);
+        let (top, stem) = (enter[0], enter[1]);
+        assert_eq!(top.0, right_bracket.x + right_bracket.width + options.gap);
+        assert_eq!(top.1, right_bracket.y);
+        assert_eq!(stem.0, hash.x + hash.width + options.gap);
+        assert_eq!(stem.1, top.1);
+        assert_eq!(stem.3, hash.y + hash.height - top.1);
+        assert_eq!(top.0 + top.2, stem.0 + stem.2);
+        assert_eq!(svg.matches("<path").count(), 1);
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// 手势类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GestureKind {
+    /// 短按
+    Tap,
+    /// 双击，第二次短按时代替tap发送
+    DoubleTap,
+    /// 长按，松开时发送
+    LongPress,
+}
+
+/// 手势判断阈值(ms)
+#[derive(Debug, Clone)]
+pub struct GestureThresholds {
+    /// 按住不超过该时间视为短按
+    pub tap_max_ms: u64,
+    /// 两次短按的间隔(上次松开到这次按下)不超过该时间视为双击
+    pub double_tap_interval_ms: u64,
+    /// 按住不少于该时间视为长按
+    pub long_press_ms: u64,
+}
+
+/// 手势识别器，根据按下和松开的时间判断手势
+pub struct GestureDetector {
+    /// 每个按键上次短按松开的时间，用于判断双击
+    last_taps: HashMap<String, SystemTime>,
+}
+
+impl GestureDetector {
+    pub fn new() -> Self {
+        Self {
+            last_taps: HashMap::new(),
+        }
+    }
+
+    /// 按键松开时调用，pressed和released为按下和松开的时间
+    pub fn on_release(
+        &mut self,
+        name: &str,
+        pressed: SystemTime,
+        released: SystemTime,
+        thresholds: &GestureThresholds,
+    ) -> Option<GestureKind> {
+        let held = released.duration_since(pressed).unwrap_or(Duration::ZERO);
+        if held >= Duration::from_millis(thresholds.long_press_ms) {
+            self.last_taps.remove(name);
+            return Some(GestureKind::LongPress);
+        }
+        if held > Duration::from_millis(thresholds.tap_max_ms) {
+            self.last_taps.remove(name);
+            return None;
+        }
+        // 短按，判断与上一次短按的间隔
+        if let Some(last_tap) = self.last_taps.remove(name) {
+            let interval = pressed.duration_since(last_tap).unwrap_or(Duration::MAX);
+            if interval <= Duration::from_millis(thresholds.double_tap_interval_ms) {
+                return Some(GestureKind::DoubleTap);
+            }
+        }
+        self.last_taps.insert(name.to_string(), released);
+        Some(GestureKind::Tap)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const THRESHOLDS: GestureThresholds = GestureThresholds {
+        tap_max_ms: 200,
+        double_tap_interval_ms: 300,
+        long_press_ms: 500,
+    };
+
+    /// 模拟的按键时间线，依次输入(按下时间, 松开时间)，返回每次松开的手势
+    fn run(name: &str, presses: &[(u64, u64)]) -> Vec<Option<GestureKind>> {
+        let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut detector = GestureDetector::new();
+        presses
+            .iter()
+            .map(|(pressed, released)| {
+                detector.on_release(name, at(*pressed), at(*released), &THRESHOLDS)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn short_press_is_tap() {
+        assert_eq!(run("a", &[(0, 50)]), vec![Some(GestureKind::Tap)]);
+    }
+
+    #[test]
+    fn tap_boundary_is_inclusive() {
+        assert_eq!(run("a", &[(0, 200)]), vec![Some(GestureKind::Tap)]);
+        assert_eq!(run("a", &[(0, 201)]), vec![None]);
+    }
+
+    #[test]
+    fn hold_between_tap_and_long_press_is_nothing() {
+        assert_eq!(run("a", &[(0, 350)]), vec![None]);
+        assert_eq!(run("a", &[(0, 499)]), vec![None]);
+    }
+
+    #[test]
+    fn long_press_boundary_is_inclusive() {
+        assert_eq!(run("a", &[(0, 500)]), vec![Some(GestureKind::LongPress)]);
+        assert_eq!(run("a", &[(0, 3000)]), vec![Some(GestureKind::LongPress)]);
+    }
+
+    #[test]
+    fn second_tap_within_interval_is_double_tap() {
+        assert_eq!(
+            run("a", &[(0, 50), (150, 200)]),
+            vec![Some(GestureKind::Tap), Some(GestureKind::DoubleTap)]
+        );
+    }
+
+    #[test]
+    fn double_tap_interval_is_measured_from_release_to_press() {
+        // 松开于50ms，间隔300ms为双击，301ms为新的短按
+        assert_eq!(
+            run("a", &[(0, 50), (350, 400)]),
+            vec![Some(GestureKind::Tap), Some(GestureKind::DoubleTap)]
+        );
+        assert_eq!(
+            run("a", &[(0, 50), (351, 400)]),
+            vec![Some(GestureKind::Tap), Some(GestureKind::Tap)]
+        );
+    }
+
+    #[test]
+    fn third_tap_starts_a_new_sequence() {
+        assert_eq!(
+            run("a", &[(0, 50), (100, 150), (200, 250), (300, 350)]),
+            vec![
+                Some(GestureKind::Tap),
+                Some(GestureKind::DoubleTap),
+                Some(GestureKind::Tap),
+                Some(GestureKind::DoubleTap),
+            ]
+        );
+    }
+
+    #[test]
+    fn long_press_or_hold_breaks_double_tap() {
+        assert_eq!(
+            run("a", &[(0, 50), (100, 700), (750, 800)]),
+            vec![
+                Some(GestureKind::Tap),
+                Some(GestureKind::LongPress),
+                Some(GestureKind::Tap),
+            ]
+        );
+        assert_eq!(
+            run("a", &[(0, 50), (100, 400), (450, 500)]),
+            vec![Some(GestureKind::Tap), None, Some(GestureKind::Tap)]
+        );
+    }
+
+    #[test]
+    fn double_tap_is_tracked_per_key() {
+        let start = SystemTime::UNIX_EPOCH;
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let mut detector = GestureDetector::new();
+        assert_eq!(
+            detector.on_release("a", at(0), at(50), &THRESHOLDS),
+            Some(GestureKind::Tap)
+        );
+        assert_eq!(
+            detector.on_release("b", at(100), at(150), &THRESHOLDS),
+            Some(GestureKind::Tap)
+        );
+        assert_eq!(
+            detector.on_release("a", at(200), at(250), &THRESHOLDS),
+            Some(GestureKind::DoubleTap)
+        );
+    }
+
+    #[test]
+    fn release_before_press_is_treated_as_zero_hold() {
+        assert_eq!(run("a", &[(100, 50)]), vec![Some(GestureKind::Tap)]);
+    }
+}
@@ -0,0 +1,357 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::sync::broadcast::Sender;
+
+use crate::{
+    inputs::{self, InputInfo, InputMessage, InputSource},
+    message::{KeySender, Message},
+    monitor,
+    mouse::MotionSender,
+    CONFIG,
+};
+
+/// tap不指定时长时按住的时间
+const DEFAULT_TAP_DURATION: Duration = Duration::from_millis(50);
+/// 脚本中的命令
+const SCRIPT_COMMANDS: [&str; 6] = ["press", "release", "tap", "wait", "move", "scroll"];
+
+#[derive(Debug, Serialize)]
+pub enum InjectError {
+    /// 设置中没有开启注入
+    DisabledError,
+    /// 脚本格式错误
+    InvalidScriptError,
+}
+
+/// 脚本中的一步
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ScriptStep {
+    Input(InputSource, InputInfo),
+    Wait(Duration),
+}
+
+/// 设置中是否开启了注入
+fn check_enabled() -> Result<(), InjectError> {
+    if unsafe { CONFIG.lock().unwrap().injection_enable } {
+        Ok(())
+    } else {
+        Err(InjectError::DisabledError)
+    }
+}
+
+/// 解析输入脚本，每行为一步或用逗号、分号分隔的多步，时长格式为200ms、1.5s或毫秒数
+/// - press <按键名> [时长]：按下，然后等待
+/// - release <按键名> [时长]：松开，然后等待
+/// - tap <按键名> [时长]：按下，等待时长(默认50ms)后松开
+/// - wait <时长>
+/// - move <x> <y> [时长]：鼠标移动到全局坐标
+/// - scroll <dx> <dy> [时长]：滚轮
+///
+/// 按键名为keys.rs中的名字，mouse_开头的为鼠标按键
+/// 逗号和分号后为命令时才作为分隔，如"press w 200ms, release w"
+/// 命令后的第一个参数可以是逗号或分号，如"tap ,"按下逗号键，"tap ,; tap a"
+pub fn parse_script(script: &str) -> Result<Vec<ScriptStep>, InjectError> {
+    let mut steps = Vec::new();
+    for step in script.lines().flat_map(split_steps) {
+        let words: Vec<&str> = step.split_whitespace().collect();
+        let (command, args) = match words.split_first() {
+            Some(split) => split,
+            None => continue,
+        };
+        let command = command.to_lowercase();
+        // 命令参数的数量，参数后可以再跟一个等待时长
+        let count = match command.as_str() {
+            "wait" => 0,
+            "move" | "scroll" => 2,
+            _ => 1,
+        };
+        if args.len() < count || args.len() > count + 1 {
+            return Err(InjectError::InvalidScriptError);
+        }
+        let (args, rest) = args.split_at(count);
+        let wait = rest.first().map(|text| parse_duration(text)).transpose()?;
+        match (command.as_str(), args) {
+            ("press", [name]) => steps.push(pressing_step(name, true)),
+            ("release", [name]) => steps.push(pressing_step(name, false)),
+            ("tap", [name]) => {
+                steps.push(pressing_step(name, true));
+                steps.push(ScriptStep::Wait(wait.unwrap_or(DEFAULT_TAP_DURATION)));
+                steps.push(pressing_step(name, false));
+                continue;
+            }
+            ("wait", []) if wait.is_some() => {}
+            ("move", [x, y]) => {
+                let x = parse_number(x)?;
+                let y = parse_number(y)?;
+                steps.push(move_step(x, y));
+            }
+            ("scroll", [delta_x, delta_y]) => {
+                let delta_x = parse_number(delta_x)? as i64;
+                let delta_y = parse_number(delta_y)? as i64;
+                steps.push(ScriptStep::Input(
+                    InputSource::MouseWheel,
+                    InputInfo::Roll { delta_x, delta_y },
+                ));
+            }
+            _ => return Err(InjectError::InvalidScriptError),
+        }
+        if let Some(wait) = wait {
+            steps.push(ScriptStep::Wait(wait));
+        }
+    }
+    Ok(steps)
+}
+
+/// 按逗号和分号将一行分为多步，分隔符前需要已有命令和参数，分隔符后需要是命令
+fn split_steps(line: &str) -> Vec<&str> {
+    let mut steps = Vec::new();
+    let mut start = 0;
+    for (index, c) in line.char_indices() {
+        if c != ',' && c != ';' || index < start {
+            continue;
+        }
+        let has_args = line[start..index].split_whitespace().count() >= 2;
+        let next_command = line[index + 1..]
+            .split_whitespace()
+            .next()
+            .is_some_and(|word| SCRIPT_COMMANDS.contains(&word.to_lowercase().as_str()));
+        if has_args && next_command {
+            steps.push(&line[start..index]);
+            start = index + 1;
+        }
+    }
+    steps.push(&line[start..]);
+    steps
+}
+
+fn pressing_step(name: &str, pressing: bool) -> ScriptStep {
+    let source = if name.starts_with("mouse_") {
+        InputSource::MouseButton
+    } else {
+        InputSource::Keyboard
+    };
+    ScriptStep::Input(
+        source,
+        InputInfo::Pressing {
+            name: name.to_string(),
+            pressing,
+            held_ms: None,
+            physical: Some(name.to_string()),
+            label: None,
+        },
+    )
+}
+
+fn move_step(x: f64, y: f64) -> ScriptStep {
+    let (screen_size, monitor, local) = match monitor::locate(x, y) {
+        Some((monitor, local)) => (
+            (monitor.width as u64, monitor.height as u64),
+            Some(monitor.index),
+            Some(local),
+        ),
+        None => (rdev::display_size().unwrap_or((1920, 1080)), None, None),
+    };
+    ScriptStep::Input(
+        InputSource::MouseMove,
+        InputInfo::Coord {
+            x,
+            y,
+            screen_size,
+            monitor,
+            local,
+        },
+    )
+}
+
+fn parse_number(text: &str) -> Result<f64, InjectError> {
+    text.parse::<f64>()
+        .map_err(|_| InjectError::InvalidScriptError)
+}
+
+/// 解析时长，支持200ms、1.5s和毫秒数
+fn parse_duration(text: &str) -> Result<Duration, InjectError> {
+    let text = text.to_lowercase();
+    let (number, scale) = if let Some(ms) = text.strip_suffix("ms") {
+        (ms, 0.001)
+    } else if let Some(s) = text.strip_suffix('s') {
+        (s, 1.0)
+    } else {
+        (text.as_str(), 0.001)
+    };
+    let value = parse_number(number)?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(InjectError::InvalidScriptError);
+    }
+    Ok(Duration::from_secs_f64(value * scale))
+}
+
+/// 发送一条模拟输入
+fn send(
+    sender: &Sender<Message>,
+    key_sender: &KeySender,
+    motion_sender: &MotionSender,
+    mut message: InputMessage,
+) {
+    message.synthetic = true;
+    inputs::send_input(sender, key_sender, motion_sender, message);
+}
+
+/// 立即发送输入消息，消息会被标记为模拟输入
+pub fn inject_messages(
+    sender: &Sender<Message>,
+    key_sender: &KeySender,
+    motion_sender: &MotionSender,
+    messages: Vec<InputMessage>,
+) -> Result<(), InjectError> {
+    check_enabled()?;
+    for message in messages {
+        send(sender, key_sender, motion_sender, message);
+    }
+    Ok(())
+}
+
+/// 解析脚本并在后台按顺序发送，脚本格式错误时不发送
+pub fn inject_script(
+    sender: &Sender<Message>,
+    key_sender: &KeySender,
+    motion_sender: &MotionSender,
+    script: &str,
+) -> Result<(), InjectError> {
+    check_enabled()?;
+    let steps = parse_script(script)?;
+    let sender = sender.clone();
+    let key_sender = key_sender.clone();
+    let motion_sender = motion_sender.clone();
+    tokio::spawn(async move {
+        for step in steps {
+            match step {
+                ScriptStep::Input(source, info) => send(
+                    &sender,
+                    &key_sender,
+                    &motion_sender,
+                    InputMessage {
+                        source,
+                        info,
+                        time: SystemTime::now(),
+                        synthetic: true,
+                    },
+                ),
+                ScriptStep::Wait(duration) => tokio::time::sleep(duration).await,
+            }
+        }
+    });
+    Ok(())
+}
+
+/// 发送模拟输入消息，用于不按键测试预设
+#[tauri::command]
+pub async fn inject_input(
+    state: State<'_, Sender<Message>>,
+    key_state: State<'_, KeySender>,
+    motion_state: State<'_, MotionSender>,
+    messages: Vec<InputMessage>,
+) -> Result<(), InjectError> {
+    inject_messages(
+        state.inner(),
+        key_state.inner(),
+        motion_state.inner(),
+        messages,
+    )
+}
+
+/// 运行输入脚本，如"press w 200ms\nrelease w"
+#[tauri::command]
+pub async fn inject_input_script(
+    state: State<'_, Sender<Message>>,
+    key_state: State<'_, KeySender>,
+    motion_state: State<'_, MotionSender>,
+    script: String,
+) -> Result<(), InjectError> {
+    inject_script(
+        state.inner(),
+        key_state.inner(),
+        motion_state.inner(),
+        &script,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 脚本中按下和松开的按键，等待以wait(ms)表示
+    fn describe(script: &str) -> Vec<String> {
+        parse_script(script)
+            .unwrap()
+            .into_iter()
+            .map(|step| match step {
+                ScriptStep::Input(_, InputInfo::Pressing { name, pressing, .. }) => {
+                    format!("{} {}", if pressing { "press" } else { "release" }, name)
+                }
+                ScriptStep::Input(_, info) => format!("{:?}", info),
+                ScriptStep::Wait(duration) => format!("wait {}", duration.as_millis()),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn steps_are_split_by_lines() {
+        assert_eq!(
+            describe("press w 200ms\r\n\n  release w\nwait 1.5s"),
+            vec!["press w", "wait 200", "release w", "wait 1500"]
+        );
+    }
+
+    #[test]
+    fn comma_and_semicolon_keys() {
+        assert_eq!(
+            describe("tap , 30\ntap ;"),
+            vec![
+                "press ,",
+                "wait 30",
+                "release ,",
+                "press ;",
+                "wait 50",
+                "release ;"
+            ]
+        );
+    }
+
+    #[test]
+    fn steps_are_split_by_commas_and_semicolons() {
+        assert_eq!(
+            describe("press w 200ms, release w"),
+            vec!["press w", "wait 200", "release w"]
+        );
+        assert_eq!(
+            describe("tap a;tap b 10 ; wait 1s"),
+            vec![
+                "press a",
+                "wait 50",
+                "release a",
+                "press b",
+                "wait 10",
+                "release b",
+                "wait 1000"
+            ]
+        );
+    }
+
+    #[test]
+    fn separators_after_comma_and_semicolon_keys() {
+        assert_eq!(
+            describe("tap ,, tap ;; press ,"),
+            vec![
+                "press ,",
+                "wait 50",
+                "release ,",
+                "press ;",
+                "wait 50",
+                "release ;",
+                "press ,"
+            ]
+        );
+    }
+}
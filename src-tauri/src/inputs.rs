@@ -1,16 +1,52 @@
-use std::{collections::HashMap, time::SystemTime};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime},
+};
 
 use rdev::{listen, Button, Event, Key};
 use serde::{Deserialize, Serialize};
 use serde_with::{formats::Flexible, serde_as, TimestampMilliSeconds};
 use tokio::sync::broadcast::Sender;
 
+#[cfg(target_os = "windows")]
+use crate::altgr::{AltGrFilter, PHANTOM_CTRL_MAX_INTERVAL};
+#[cfg(target_os = "linux")]
+use crate::evdev_backend;
 use crate::{
-    keys,
-    message::{Message, MessageData, MessageType},
-    CONFIG,
+    config::KeyBindMode,
+    coverage, focus,
+    gesture::{GestureDetector, GestureKind, GestureThresholds},
+    keycast::KeyCast,
+    keys, layout,
+    message::{KeySender, Message, MessageData, MessageType},
+    monitor,
+    mouse::{self, MotionSender, MotionTracker},
+    stats, usage, watchdog, CONFIG,
 };
 
+/// 需要按键重复消息的订阅者(开启repeat的客户端、录制)数量，为0时不发送重复消息
+static REPEAT_SUBSCRIBERS: AtomicUsize = AtomicUsize::new(0);
+
+/// 按键重复消息的订阅，drop时取消
+pub struct RepeatSubscription;
+
+impl Drop for RepeatSubscription {
+    fn drop(&mut self) {
+        REPEAT_SUBSCRIBERS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 订阅按键重复消息，持有返回值期间会发送重复消息
+pub fn subscribe_repeat() -> RepeatSubscription {
+    REPEAT_SUBSCRIBERS.fetch_add(1, Ordering::Relaxed);
+    RepeatSubscription
+}
+
 /// 输入开源
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -19,6 +55,7 @@ pub enum InputSource {
     MouseMove,
     MouseButton,
     MouseWheel,
+    Gamepad,
 }
 
 /// 输入的信息
@@ -26,18 +63,109 @@ pub enum InputSource {
 #[serde(rename_all = "snake_case")]
 #[serde(untagged)]
 pub enum InputInfo {
-    /// 按键名，是否正在按住
+    /// 按键名，是否正在按住，松开时附带按住的时长(ms)
+    /// name为预设绑定的按键名，由设置中的key_bind_mode决定使用physical还是label
     #[serde(rename_all = "snake_case")]
-    Pressing { name: String, pressing: bool },
-    /// 鼠标坐标和屏幕大小
+    Pressing {
+        name: String,
+        pressing: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        held_ms: Option<u64>,
+        /// 按键的物理位置，即keys.rs中的名字
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        physical: Option<String>,
+        /// 当前键盘布局下不按修饰键时按键的字符，没有字符时为None
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        label: Option<String>,
+    },
+    /// 鼠标坐标和所在显示器的大小
     Coord {
         x: f64,
         y: f64,
-        /// 备用
+        /// 鼠标所在显示器的大小，获取不到显示器时为主屏幕大小
         screen_size: (u64, u64),
+        /// 鼠标所在显示器的序号
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        monitor: Option<usize>,
+        /// 在所在显示器内归一化(0~1)的坐标
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        local: Option<(f64, f64)>,
     },
     /// 滚轮方向
     Roll { delta_x: i64, delta_y: i64 },
+    /// 组合键，按住修饰键时按下其他按键触发，是否为设置中需要高亮的组合键
+    Chord {
+        keys: Vec<String>,
+        modifiers: Vec<String>,
+        highlight: bool,
+    },
+    /// 手柄按键，gamepad为手柄的序号
+    GamepadButton {
+        gamepad: usize,
+        button: String,
+        pressing: bool,
+    },
+    /// 手柄摇杆和扳机，摇杆为-1~1，扳机为0~1
+    GamepadAxis {
+        gamepad: usize,
+        axis: String,
+        value: f64,
+    },
+    /// 手柄连接和断开
+    GamepadConnection {
+        gamepad: usize,
+        name: String,
+        connected: bool,
+    },
+    /// 按住键盘按键时系统发送的重复按下，repeat为本次按住的重复次数(从1开始)
+    /// 默认不发送到客户端，需要在连接时指定
+    Repeat { name: String, repeat: u64 },
+    /// 手势，松开按键时根据按住时长和间隔判断
+    Gesture { name: String, gesture: GestureKind },
+    /// 鼠标相对移动，移动距离超出设置的半径后发送
+    Motion {
+        /// 相对上次发送时的位移(px)
+        dx: f64,
+        dy: f64,
+        /// 单位方向向量(屏幕坐标，y轴向下)
+        direction: (f64, f64),
+        /// 移动速度(px/s)
+        speed: f64,
+        /// 八方向
+        bucket: MotionDirection,
+    },
+}
+
+/// 鼠标移动的八方向
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MotionDirection {
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+    Up,
+    UpRight,
+}
+
+impl MotionDirection {
+    /// 通过位移获取方向，按角度划分为八个45°的区间
+    pub fn from_delta(dx: f64, dy: f64) -> Self {
+        let angle = dy.atan2(dx);
+        let index = (angle / std::f64::consts::FRAC_PI_4).round() as i64;
+        match index.rem_euclid(8) {
+            0 => Self::Right,
+            1 => Self::DownRight,
+            2 => Self::Down,
+            3 => Self::DownLeft,
+            4 => Self::Left,
+            5 => Self::UpLeft,
+            6 => Self::Up,
+            _ => Self::UpRight,
+        }
+    }
 }
 
 /// 输入消息，用于打包通过sse发送到client
@@ -51,6 +179,9 @@ pub struct InputMessage {
     /// 输入时间
     #[serde_as(as = "TimestampMilliSeconds<String, Flexible>")]
     pub time: SystemTime,
+    /// 是否为通过注入接口发送的模拟输入
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub synthetic: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -60,14 +191,31 @@ pub enum KeyButton {
 }
 
 pub struct Handler {
-    /// 当前按下的键盘按键
-    pub pressing_keys: HashMap<String, bool>,
-    /// 当前按下的鼠标按键
-    pub pressing_mouse_buttons: HashMap<String, bool>,
+    /// 当前按下的键盘按键和按下的时间
+    pub pressing_keys: HashMap<String, SystemTime>,
+    /// 当前按下的鼠标按键和按下的时间
+    pub pressing_mouse_buttons: HashMap<String, SystemTime>,
+    /// 当前按下的键盘按键按下时输入的字符，松开事件中没有字符
+    pub pressing_labels: HashMap<String, String>,
+    /// 按住的键盘按键最后一次收到重复按下事件的时间，没有重复过的按键不在其中
+    pub last_seen: HashMap<String, SystemTime>,
+    /// 当前按下的键盘按键的重复次数
+    pub repeat_counts: HashMap<String, u64>,
     /// 输入消息发送器，发送到服务端
     pub sender: Sender<Message>,
-    /// 屏幕尺寸，备用
+    /// 按键和按钮按下、松开消息的发送器，不会丢弃消息
+    pub key_sender: KeySender,
+    /// 屏幕尺寸，获取不到鼠标所在的显示器时使用
     pub screen_size: (u64, u64),
+    /// 按键记录聚合器
+    pub key_cast: KeyCast,
+    /// 手势识别器
+    pub gesture: GestureDetector,
+    /// AltGr虚拟左Ctrl过滤器
+    #[cfg(target_os = "windows")]
+    pub alt_gr: AltGrFilter,
+    /// 鼠标相对移动检测
+    pub motion: MotionTracker,
     // pub message_sender: Sender<InputMessage>,
     // 按键回调接收器
     // pub receiver: UnboundedReceiver<Event>,
@@ -84,18 +232,36 @@ impl Handler {
         // mouse_move_enable: &'static bool,
         // message_sender: Sender<InputMessage>,
         sender: Sender<Message>,
+        key_sender: KeySender,
     ) -> Self {
         Self {
             pressing_keys: HashMap::new(),
             pressing_mouse_buttons: HashMap::new(),
+            pressing_labels: HashMap::new(),
+            last_seen: HashMap::new(),
+            repeat_counts: HashMap::new(),
             sender,
+            key_sender,
             screen_size: rdev::display_size().unwrap_or((1920, 1080)),
+            key_cast: KeyCast::new(),
+            gesture: GestureDetector::new(),
+            #[cfg(target_os = "windows")]
+            alt_gr: AltGrFilter::new(),
+            motion: MotionTracker::new(),
             // enable: enable.clone(),
             // mouse_move_enable: mouse_move_enable.clone(),
             // message_sender,
         }
     }
 
+    // 发送按键和按钮的按下、松开消息
+    fn send_key(&self, data: InputMessage) {
+        self.key_sender.send(Message {
+            r#type: MessageType::Input,
+            data: MessageData::InputMessage(data),
+        });
+    }
+
     fn send(&self, data: InputMessage) {
         if let Err(_) = self.sender.send(Message {
             r#type: MessageType::Input,
@@ -105,17 +271,79 @@ impl Handler {
         };
     }
 
+    /// 处理一个输入事件，rdev和evdev共用
+    pub fn handle(&mut self, event: Event) {
+        if !unsafe { CONFIG.lock().unwrap().enable } {
+            return ();
+        }
+        // 焦点在需要暂停的应用中
+        // 按下时重新检查焦点，避免切换窗口后到下一次定时检查前按下的按键被发送
+        let press = matches!(
+            event.event_type,
+            rdev::EventType::KeyPress(_) | rdev::EventType::ButtonPress(_)
+        );
+        if press && focus::check(focus::PRESS_CHECK_TTL) {
+            if focus::is_paused() {
+                self.release_all(event.time);
+            }
+            let _ = self.sender.send(focus::get_message());
+        }
+        if focus::is_paused() {
+            return;
+        }
+        // Windows下左Ctrl的事件可能被暂存，由start中的线程到时发送
+        #[cfg(target_os = "windows")]
+        let events = self.alt_gr.filter(event);
+        #[cfg(not(target_os = "windows"))]
+        let events = [event];
+        for event in events {
+            self.dispatch(event);
+        }
+    }
+
+    /// 发送AltGr过滤器中等待超时的左Ctrl事件
+    #[cfg(target_os = "windows")]
+    pub fn flush_alt_gr(&mut self, now: SystemTime) {
+        if let Some(event) = self.alt_gr.flush(now) {
+            self.dispatch(event);
+        }
+    }
+
+    fn dispatch(&mut self, event: Event) {
+        match event.event_type {
+            rdev::EventType::KeyPress(key) => {
+                self.on_press(event, InputSource::Keyboard, KeyButton::Key(key))
+            }
+            rdev::EventType::KeyRelease(key) => {
+                self.on_release(event, InputSource::Keyboard, KeyButton::Key(key))
+            }
+            rdev::EventType::ButtonPress(button) => {
+                self.on_press(event, InputSource::MouseButton, KeyButton::Button(button))
+            }
+            rdev::EventType::ButtonRelease(button) => {
+                self.on_release(event, InputSource::MouseButton, KeyButton::Button(button))
+            }
+            rdev::EventType::MouseMove { x, y } => {
+                // 如果不处理鼠标移动事件就返回
+                if !unsafe { CONFIG.lock().unwrap().mouse_move_enable } {
+                    return ();
+                }
+                self.on_mouse_move(x, y, event.time);
+            }
+            rdev::EventType::Wheel { delta_x, delta_y } => {
+                self.on_mouse_scroll(delta_x, delta_y, event.time)
+            }
+        }
+    }
+
     // 按下或按住按键时
     pub fn on_press(&mut self, event: Event, source: InputSource, key_button: KeyButton) {
-        let name_res = match key_button {
-            KeyButton::Key(key) => get_key_name(key),
-            KeyButton::Button(button) => get_mouse_button_name(button),
+        let key = match key_button {
+            KeyButton::Key(key) => Some(key),
+            KeyButton::Button(_) => None,
         };
-        if let Err(keycode) = name_res {
-            eprintln!("name error: {}", keycode);
-            return;
-        }
-        let name = name_res.unwrap();
+        let name = get_name(key_button);
+        let name = name.as_str();
         // 判断是键盘按键还是鼠标按钮，并获取相应hashmap的引用
         let keymap = if let InputSource::Keyboard = source {
             &mut self.pressing_keys
@@ -124,29 +352,94 @@ impl Handler {
         };
         // 按键之前不是按住状态，加入按住状态并发送按下消息
         if let None = keymap.get(name) {
-            keymap.insert(name.to_string(), true);
-            self.send(InputMessage {
-                source,
+            keymap.insert(name.to_string(), event.time);
+            let label = match key {
+                Some(key) => get_layout_label(key, event.name.as_deref()),
+                None => None,
+            };
+            if let Some(label) = &label {
+                self.pressing_labels.insert(name.to_string(), label.clone());
+            }
+            let bind_name = get_bind_name(name, label.as_deref()).to_string();
+            stats::record(name, &source);
+            usage::record(name, &source);
+            coverage::record(&bind_name, &source);
+            self.send_key(InputMessage {
+                source: source.clone(),
                 info: InputInfo::Pressing {
-                    name: name.to_string(),
+                    name: bind_name,
                     pressing: true,
+                    held_ms: None,
+                    physical: Some(name.to_string()),
+                    label,
                 },
                 time: event.time,
+                synthetic: false,
+            });
+            // 键盘按键按下时判断组合键并记录按键
+            if let InputSource::Keyboard = source {
+                self.detect_chord(name, event.time);
+                self.cast_key(name, event.name.as_deref(), event.time);
+            }
+        } else if let InputSource::Keyboard = source {
+            // 已经按住的键盘按键再次按下为系统的重复按下，不改变按住状态
+            // 记录重复的时间，正在重复的按键不会被当作卡住
+            self.last_seen.insert(name.to_string(), event.time);
+            let repeat = self.repeat_counts.entry(name.to_string()).or_insert(0);
+            *repeat += 1;
+            let repeat = *repeat;
+            // 没有订阅者时不发送，避免高频的重复消息挤占广播通道
+            if REPEAT_SUBSCRIBERS.load(Ordering::Relaxed) == 0 {
+                return;
+            }
+            let label = self.pressing_labels.get(name).cloned();
+            self.send(InputMessage {
+                source,
+                info: InputInfo::Repeat {
+                    name: get_bind_name(name, label.as_deref()).to_string(),
+                    repeat,
+                },
+                time: event.time,
+                synthetic: false,
             });
         }
     }
 
-    // 放开按键时
-    pub fn on_release(&mut self, event: Event, source: InputSource, key_button: KeyButton) {
-        let name_res = match key_button {
-            KeyButton::Key(key) => get_key_name(key),
-            KeyButton::Button(button) => get_mouse_button_name(button),
-        };
-        if let Err(keycode) = name_res {
-            eprintln!("name error: {}", keycode);
+    // 按下非修饰键且有修饰键按住时，发送组合键消息
+    fn detect_chord(&self, name: &str, time: SystemTime) {
+        if let Some(_) = get_modifier(name) {
+            return;
+        }
+        let modifiers = self.get_pressing_modifiers();
+        if modifiers.is_empty() {
             return;
         }
-        let name = name_res.unwrap();
+        let mut keys: Vec<String> = self
+            .pressing_keys
+            .keys()
+            .filter(|key| get_modifier(key).is_none())
+            .cloned()
+            .collect();
+        keys.sort();
+        let highlight = unsafe { CONFIG.lock().unwrap().highlight_chords.clone() }
+            .iter()
+            .any(|chord| is_same_chord(chord, &modifiers, &keys));
+        self.send(InputMessage {
+            source: InputSource::Keyboard,
+            info: InputInfo::Chord {
+                keys,
+                modifiers,
+                highlight,
+            },
+            time,
+            synthetic: false,
+        });
+    }
+
+    // 放开按键时
+    pub fn on_release(&mut self, event: Event, source: InputSource, key_button: KeyButton) {
+        let name = get_name(key_button);
+        let name = name.as_str();
         // 判断是键盘按键还是鼠标按钮，并获取相应hashmap的引用
         let keymap = if let InputSource::Keyboard = source {
             &mut self.pressing_keys
@@ -154,29 +447,234 @@ impl Handler {
             &mut self.pressing_mouse_buttons
         };
         // 按键之前是按住状态，去除按住状态并发送抬起消息
-        if let Some(_) = keymap.get(name) {
-            keymap.remove(&name.to_string());
-            self.send(InputMessage {
-                source,
+        if let Some(pressed) = keymap.remove(name) {
+            self.last_seen.remove(name);
+            self.repeat_counts.remove(name);
+            let held_ms = event
+                .time
+                .duration_since(pressed)
+                .map(|held| held.as_millis() as u64)
+                .ok();
+            let label = self.pressing_labels.remove(name);
+            let bind_name = get_bind_name(name, label.as_deref()).to_string();
+            self.send_key(InputMessage {
+                source: source.clone(),
                 info: InputInfo::Pressing {
-                    name: name.to_string(),
+                    name: bind_name.clone(),
                     pressing: false,
+                    held_ms,
+                    physical: Some(name.to_string()),
+                    label,
                 },
                 time: event.time,
+                synthetic: false,
+            });
+            self.detect_gesture(&bind_name, source, pressed, event.time);
+        }
+    }
+
+    /// 松开按住超过max_hold的键盘和鼠标按键，返回松开的按键数量
+    /// 用于恢复松开事件丢失(焦点被抢、锁屏、UAC提示等)而一直处于按住状态的按键
+    /// 正在重复的按键从最后一次重复按下开始计算，一直按住并重复的按键不会松开
+    pub fn expire_keys(&mut self, now: SystemTime, max_hold: Duration) -> usize {
+        let expired: Vec<String> = self
+            .pressing_keys
+            .iter()
+            .chain(self.pressing_mouse_buttons.iter())
+            .filter(|(name, pressed)| {
+                let seen = match self.last_seen.get(*name) {
+                    Some(seen) => (*seen).max(**pressed),
+                    None => **pressed,
+                };
+                now.duration_since(seen).unwrap_or(Duration::ZERO) > max_hold
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in expired.iter() {
+            self.release_key(name, now);
+        }
+        expired.len()
+    }
+
+    /// 松开所有按住的按键并清除所有状态，用于开关切换、休眠唤醒后
+    pub fn release_all(&mut self, time: SystemTime) {
+        let names: Vec<String> = self
+            .pressing_keys
+            .keys()
+            .chain(self.pressing_mouse_buttons.keys())
+            .cloned()
+            .collect();
+        for name in names.iter() {
+            self.release_key(name, time);
+        }
+        self.last_seen.clear();
+        self.repeat_counts.clear();
+        self.pressing_labels.clear();
+        self.gesture = GestureDetector::new();
+        #[cfg(target_os = "windows")]
+        {
+            self.alt_gr = AltGrFilter::new();
+        }
+        self.motion.reset();
+    }
+
+    // 去除按键的按住状态并发送抬起消息
+    fn release_key(&mut self, name: &str, time: SystemTime) {
+        self.last_seen.remove(name);
+        self.repeat_counts.remove(name);
+        let source = if let Some(_) = self.pressing_keys.remove(name) {
+            InputSource::Keyboard
+        } else if let Some(_) = self.pressing_mouse_buttons.remove(name) {
+            InputSource::MouseButton
+        } else {
+            return;
+        };
+        self.send_forced_release(name, source, time);
+    }
+
+    // 发送不是由输入事件产生的抬起消息
+    fn send_forced_release(&mut self, name: &str, source: InputSource, time: SystemTime) {
+        let label = self.pressing_labels.remove(name);
+        self.send_key(InputMessage {
+            source,
+            info: InputInfo::Pressing {
+                name: get_bind_name(name, label.as_deref()).to_string(),
+                pressing: false,
+                held_ms: None,
+                physical: Some(name.to_string()),
+                label,
+            },
+            time,
+            synthetic: false,
+        });
+    }
+
+    // 放开按键时判断手势并发送手势消息
+    fn detect_gesture(
+        &mut self,
+        name: &str,
+        source: InputSource,
+        pressed: SystemTime,
+        released: SystemTime,
+    ) {
+        let thresholds = unsafe {
+            let config = CONFIG.lock().unwrap();
+            if !config.gesture_enable {
+                return;
+            }
+            GestureThresholds {
+                tap_max_ms: config.gesture_tap_max_ms,
+                double_tap_interval_ms: config.gesture_double_tap_interval_ms,
+                long_press_ms: config.gesture_long_press_ms,
+            }
+        };
+        if let Some(gesture) = self
+            .gesture
+            .on_release(name, pressed, released, &thresholds)
+        {
+            self.send(InputMessage {
+                source,
+                info: InputInfo::Gesture {
+                    name: name.to_string(),
+                    gesture,
+                },
+                time: released,
+                synthetic: false,
+            });
+        }
+    }
+
+    // 获取按住的修饰键，按ctrl、shift、alt、meta排序，左右两侧同时按住时只保留一个
+    fn get_pressing_modifiers(&self) -> Vec<String> {
+        let mut modifiers: Vec<String> = self
+            .pressing_keys
+            .keys()
+            .filter_map(|key| get_modifier(key))
+            .map(|modifier| modifier.to_string())
+            .collect();
+        modifiers.sort_by_key(|modifier| modifier_order(modifier));
+        modifiers.dedup();
+        modifiers
+    }
+
+    // 键盘按下时发送按键记录消息
+    fn cast_key(&mut self, name: &str, label: Option<&str>, time: SystemTime) {
+        let (enable, timeout_ms, show_text) = unsafe {
+            let config = CONFIG.lock().unwrap();
+            (
+                config.key_cast_enable,
+                config.key_cast_timeout_ms,
+                config.key_cast_show_text,
+            )
+        };
+        if !enable {
+            return;
+        }
+        let modifiers = self.get_pressing_modifiers();
+        if let Some(message) = self
+            .key_cast
+            .on_press(name, label, &modifiers, time, timeout_ms, show_text)
+        {
+            let _ = self.sender.send(Message {
+                r#type: MessageType::KeyCast,
+                data: MessageData::KeyCastMessage(message),
             });
         }
     }
 
     // 移动鼠标时
     pub fn on_mouse_move(&mut self, x: f64, y: f64, time: SystemTime) {
-        self.send(InputMessage {
+        let located = monitor::locate(x, y);
+        // 只显示指定显示器上的鼠标移动
+        if let Some(target) = unsafe { CONFIG.lock().unwrap().mouse_monitor } {
+            if located.as_ref().map(|(monitor, _)| monitor.index) != Some(target) {
+                self.motion.reset();
+                return;
+            }
+        }
+        let (screen_size, monitor, local) = match located {
+            Some((monitor, local)) => (
+                (monitor.width as u64, monitor.height as u64),
+                Some(monitor.index),
+                Some(local),
+            ),
+            None => (self.screen_size, None, None),
+        };
+        // 鼠标移动消息经过合并后从单独的通道发送
+        mouse::push_move(InputMessage {
             source: InputSource::MouseMove,
             info: InputInfo::Coord {
                 x,
                 y,
-                screen_size: self.screen_size,
+                screen_size,
+                monitor,
+                local,
+            },
+            time,
+            synthetic: false,
+        });
+        self.detect_motion(x, y, time);
+    }
+
+    // 鼠标移出半径范围时发送相对移动消息
+    fn detect_motion(&mut self, x: f64, y: f64, time: SystemTime) {
+        let radius = unsafe { CONFIG.lock().unwrap().mouse_move_radius_px } as f64;
+        let motion = match self.motion.push(x, y, time, radius) {
+            Some(motion) => motion,
+            None => return,
+        };
+        let (dx, dy, distance) = (motion.dx, motion.dy, motion.distance);
+        mouse::push_move(InputMessage {
+            source: InputSource::MouseMove,
+            info: InputInfo::Motion {
+                dx,
+                dy,
+                direction: (dx / distance, dy / distance),
+                speed: motion.speed,
+                bucket: MotionDirection::from_delta(dx, dy),
             },
             time,
+            synthetic: false,
         });
     }
 
@@ -186,49 +684,184 @@ impl Handler {
             source: InputSource::MouseWheel,
             info: InputInfo::Roll { delta_x, delta_y },
             time,
+            synthetic: false,
         });
     }
 }
 
 /// 处理输入
-pub fn start(sender: Sender<Message>) {
-    let mut handler = Handler::new(sender);
+pub fn start(sender: Sender<Message>, key_sender: KeySender) {
+    let handler = Arc::new(Mutex::new(Handler::new(sender.clone(), key_sender)));
+    let watchdog_handler = handler.clone();
+    thread::spawn(move || watchdog::run(watchdog_handler));
+    let focus_handler = handler.clone();
+    thread::spawn(move || focus::run(sender, focus_handler));
+    // 定时发送AltGr过滤器中暂存的左Ctrl
+    #[cfg(target_os = "windows")]
+    {
+        let alt_gr_handler = handler.clone();
+        thread::spawn(move || loop {
+            thread::sleep(PHANTOM_CTRL_MAX_INTERVAL);
+            alt_gr_handler
+                .lock()
+                .unwrap()
+                .flush_alt_gr(SystemTime::now());
+        });
+    }
 
-    if let Err(error) = listen(move |event| {
-        if !unsafe { CONFIG.lock().unwrap().enable } {
-            return ();
+    // Wayland下rdev无法获取输入，改为直接读取evdev设备
+    #[cfg(target_os = "linux")]
+    if evdev_backend::should_use() {
+        evdev_backend::start(handler);
+        return;
+    }
+
+    if let Err(error) = listen(move |event| handler.lock().unwrap().handle(event)) {
+        eprintln!("Error: {:?}", error)
+    }
+}
+
+/// 按消息类型选择通道发送输入消息，用于回放和注入等不经过Handler的输入
+/// 按键和按钮通过key_sender，鼠标移动合并后发送，摇杆和扳机通过motion_sender，其他通过sender
+pub fn send_input(
+    sender: &Sender<Message>,
+    key_sender: &KeySender,
+    motion_sender: &MotionSender,
+    message: InputMessage,
+) {
+    let route = |message: InputMessage| Message {
+        r#type: MessageType::Input,
+        data: MessageData::InputMessage(message),
+    };
+    match message.info {
+        InputInfo::Pressing { .. } | InputInfo::GamepadButton { .. } => {
+            key_sender.send(route(message))
         }
-        match event.event_type {
-            rdev::EventType::KeyPress(key) => {
-                handler.on_press(event, InputSource::Keyboard, KeyButton::Key(key))
-            }
-            rdev::EventType::KeyRelease(key) => {
-                handler.on_release(event, InputSource::Keyboard, KeyButton::Key(key))
-            }
-            rdev::EventType::ButtonPress(button) => {
-                handler.on_press(event, InputSource::MouseButton, KeyButton::Button(button))
-            }
-            rdev::EventType::ButtonRelease(button) => {
-                handler.on_release(event, InputSource::MouseButton, KeyButton::Button(button))
-            }
-            rdev::EventType::MouseMove { x, y } => {
-                // 如果不处理鼠标移动事件就返回
-                if !unsafe { CONFIG.lock().unwrap().mouse_move_enable } {
-                    return ();
-                }
-                handler.on_mouse_move(x, y, event.time);
-            }
-            rdev::EventType::Wheel { delta_x, delta_y } => {
-                handler.on_mouse_scroll(delta_x, delta_y, event.time)
-            }
+        InputInfo::Coord { .. } | InputInfo::Motion { .. } => mouse::push_move(message),
+        InputInfo::GamepadAxis { .. } => {
+            let _ = motion_sender.0.send(route(message));
         }
-    }) {
-        eprintln!("Error: {:?}", error)
+        _ => {
+            let _ = sender.send(route(message));
+        }
+    }
+}
+
+/// 输入设备状态(evdev)，用于前端显示和选择设备
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputDevice {
+    /// 设备路径，如/dev/input/event3
+    pub path: String,
+    /// 设备名
+    pub name: Option<String>,
+    /// 是否正在读取
+    pub opened: bool,
+    /// 打开或读取设备时的错误，如没有权限
+    pub error: Option<String>,
+}
+
+/// 前端获取输入设备状态，只有使用evdev时有设备
+#[tauri::command]
+pub fn get_input_devices() -> Vec<InputDevice> {
+    #[cfg(target_os = "linux")]
+    return evdev_backend::get_devices();
+    #[cfg(not(target_os = "linux"))]
+    Vec::new()
+}
+
+/// 获取按键对应的修饰键名，不是修饰键则返回None
+pub fn get_modifier(name: &str) -> Option<&'static str> {
+    if name.eq(keys::CONTROL_LEFT) || name.eq(keys::CONTROL_RIGHT) {
+        Some(keys::MODIFIER_CTRL)
+    } else if name.eq(keys::SHIFT_LEFT) || name.eq(keys::SHIFT_RIGHT) {
+        Some(keys::MODIFIER_SHIFT)
+    } else if name.eq(keys::ALT_LEFT) || name.eq(keys::ALT_RIGHT) {
+        Some(keys::MODIFIER_ALT)
+    } else if name.eq(keys::META_LEFT) || name.eq(keys::META_RIGHT) {
+        Some(keys::MODIFIER_META)
+    } else {
+        None
+    }
+}
+
+/// 修饰键的排列顺序
+pub fn modifier_order(modifier: &str) -> usize {
+    [
+        keys::MODIFIER_CTRL,
+        keys::MODIFIER_SHIFT,
+        keys::MODIFIER_ALT,
+        keys::MODIFIER_META,
+    ]
+    .iter()
+    .position(|m| m.eq(&modifier))
+    .unwrap_or(usize::MAX)
+}
+
+/// 判断设置中的组合键字符串(如"ctrl+shift+s")是否与按下的组合键相同
+fn is_same_chord(chord: &str, modifiers: &Vec<String>, keys: &Vec<String>) -> bool {
+    let mut chord_modifiers: Vec<String> = Vec::new();
+    let mut chord_keys: Vec<String> = Vec::new();
+    for part in chord.split('+').map(|part| part.trim().to_lowercase()) {
+        if part.is_empty() {
+            continue;
+        }
+        // 组合键字符串中也可以写具体的左右修饰键
+        match get_modifier(&part) {
+            Some(modifier) => chord_modifiers.push(modifier.to_string()),
+            None if modifier_order(&part) != usize::MAX => chord_modifiers.push(part),
+            None => chord_keys.push(part),
+        }
+    }
+    chord_modifiers.sort_by_key(|modifier| modifier_order(modifier));
+    chord_modifiers.dedup();
+    chord_keys.sort();
+    chord_modifiers.eq(modifiers) && chord_keys.eq(keys)
+}
+
+/// 获取按键在键盘布局下不按修饰键时的字符，用于显示和按label绑定
+/// 无法读取键盘布局(如macOS)时使用rdev事件的name，此时的字符受Shift等修饰键影响
+/// 只保留单个可见字符并转为小写，evdev和部分平台的事件没有name，此时返回None
+fn get_layout_label(key: Key, name: Option<&str>) -> Option<String> {
+    if let Ok(label) = layout::get_label(key) {
+        return label;
+    }
+    let name = name?;
+    let mut chars = name.chars();
+    let c = chars.next()?;
+    if chars.next().is_some() || c.is_control() || c.is_whitespace() {
+        return None;
+    }
+    Some(c.to_lowercase().collect())
+}
+
+/// 根据设置获取预设绑定的按键名，没有字符时使用物理按键名
+fn get_bind_name<'a>(physical: &'a str, label: Option<&'a str>) -> &'a str {
+    match (
+        unsafe { CONFIG.lock().unwrap().key_bind_mode.clone() },
+        label,
+    ) {
+        (KeyBindMode::Label, Some(label)) => label,
+        _ => physical,
+    }
+}
+
+/// 获取按键名，没有名字的键盘按键使用"unknown_<按键码>"，其他鼠标按键使用"mouse_<序号>"
+fn get_name(key_button: KeyButton) -> String {
+    match key_button {
+        KeyButton::Key(key) => match get_key_name(key) {
+            Ok(name) => name.to_string(),
+            Err(keycode) => format!("{}{}", keys::UNKNOWN_PREFIX, keycode),
+        },
+        KeyButton::Button(button) => match get_mouse_button_name(button) {
+            Ok(name) => name.to_string(),
+            // Unknown(1)为mouse_4，之后依次排列
+            Err(keycode) => format!("{}{}", keys::MOUSE_PREFIX, keycode + 3),
+        },
     }
 }
 
 /// 通过Button获取鼠标按键名
-fn get_mouse_button_name(button: Button) -> Result<&'static str, u32> {
+pub(crate) fn get_mouse_button_name(button: Button) -> Result<&'static str, u32> {
     match button {
         Button::Left => Ok(keys::MOUSE_LEFT),
         Button::Right => Ok(keys::MOUSE_RIGHT),
@@ -243,7 +876,7 @@ fn get_mouse_button_name(button: Button) -> Result<&'static str, u32> {
 }
 
 /// 通过Key获取按键名
-fn get_key_name(key: Key) -> Result<&'static str, u32> {
+pub(crate) fn get_key_name(key: Key) -> Result<&'static str, u32> {
     match key {
         Key::Alt => Ok(keys::ALT_LEFT),
         Key::AltGr => Ok(keys::ALT_RIGHT),
@@ -308,7 +941,7 @@ fn get_key_name(key: Key) -> Result<&'static str, u32> {
         Key::KeyU => Ok(keys::KEY_U),
         Key::KeyI => Ok(keys::KEY_I),
         Key::KeyO => Ok(keys::KEY_O),
-        Key::KeyP => Ok(keys::KP_0),
+        Key::KeyP => Ok(keys::KEY_P),
         Key::LeftBracket => Ok(keys::LEFT_BRACKET),
         Key::RightBracket => Ok(keys::RIGHT_BRACKET),
         Key::KeyA => Ok(keys::KEY_A),
@@ -323,7 +956,7 @@ fn get_key_name(key: Key) -> Result<&'static str, u32> {
         Key::SemiColon => Ok(keys::SEMICOLON),
         Key::Quote => Ok(keys::QUOTE),
         Key::BackSlash => Ok(keys::BACKSLASH),
-        Key::IntlBackslash => Err(226),
+        Key::IntlBackslash => Ok(keys::INTLBACKSLASH),
         Key::KeyZ => Ok(keys::KEY_Z),
         Key::KeyX => Ok(keys::KEY_X),
         Key::KeyC => Ok(keys::KEY_C),
@@ -352,6 +985,417 @@ fn get_key_name(key: Key) -> Result<&'static str, u32> {
         Key::Kp9 => Ok(keys::KP_9),
         Key::KpDelete => Ok(keys::KP_DELETE),
         Key::Function => Ok(keys::FUNCTION),
-        Key::Unknown(keycode) => Err(keycode),
+        Key::Unknown(keycode) => get_platform_key_name(keycode).ok_or(keycode),
+    }
+}
+
+/// 通过Windows虚拟键码获取rdev没有的按键名
+#[cfg(target_os = "windows")]
+fn get_platform_key_name(keycode: u32) -> Option<&'static str> {
+    match keycode {
+        0x7C..=0x87 => Some(get_extended_f_key(keycode - 0x7C)),
+        0x5D => Some(keys::CONTEXT_MENU),
+        0xA6 => Some(keys::BROWSER_BACK),
+        0xA7 => Some(keys::BROWSER_FORWARD),
+        0xA8 => Some(keys::BROWSER_REFRESH),
+        0xA9 => Some(keys::BROWSER_STOP),
+        0xAA => Some(keys::BROWSER_SEARCH),
+        0xAB => Some(keys::BROWSER_FAVORITES),
+        0xAC => Some(keys::BROWSER_HOME),
+        0xAD => Some(keys::VOLUME_MUTE),
+        0xAE => Some(keys::VOLUME_DOWN),
+        0xAF => Some(keys::VOLUME_UP),
+        0xB0 => Some(keys::MEDIA_NEXT),
+        0xB1 => Some(keys::MEDIA_PREV),
+        0xB2 => Some(keys::MEDIA_STOP),
+        0xB3 => Some(keys::MEDIA_PLAY_PAUSE),
+        0xB4 => Some(keys::LAUNCH_MAIL),
+        0xB5 => Some(keys::LAUNCH_MEDIA),
+        0xB6 => Some(keys::LAUNCH_APP1),
+        0xB7 => Some(keys::LAUNCH_APP2),
+        0x15 => Some(keys::KANA_MODE),
+        0x19 => Some(keys::LANG2),
+        0x1C => Some(keys::CONVERT),
+        0x1D => Some(keys::NON_CONVERT),
+        0xC1 => Some(keys::INTL_RO),
+        0xDF => Some(keys::OEM_8),
+        _ => None,
+    }
+}
+
+/// 通过X11按键码(evdev按键码+8)获取rdev没有的按键名
+#[cfg(target_os = "linux")]
+fn get_platform_key_name(keycode: u32) -> Option<&'static str> {
+    match keycode {
+        191..=202 => Some(get_extended_f_key(keycode - 191)),
+        135 => Some(keys::CONTEXT_MENU),
+        121 => Some(keys::VOLUME_MUTE),
+        122 => Some(keys::VOLUME_DOWN),
+        123 => Some(keys::VOLUME_UP),
+        171 => Some(keys::MEDIA_NEXT),
+        172 => Some(keys::MEDIA_PLAY_PAUSE),
+        173 => Some(keys::MEDIA_PREV),
+        174 => Some(keys::MEDIA_STOP),
+        166 => Some(keys::BROWSER_BACK),
+        167 => Some(keys::BROWSER_FORWARD),
+        181 => Some(keys::BROWSER_REFRESH),
+        136 => Some(keys::BROWSER_STOP),
+        225 => Some(keys::BROWSER_SEARCH),
+        164 => Some(keys::BROWSER_FAVORITES),
+        180 => Some(keys::BROWSER_HOME),
+        163 => Some(keys::LAUNCH_MAIL),
+        179 => Some(keys::LAUNCH_MEDIA),
+        165 => Some(keys::LAUNCH_APP1),
+        148 => Some(keys::LAUNCH_APP2),
+        97 => Some(keys::INTL_RO),
+        132 => Some(keys::INTL_YEN),
+        101 => Some(keys::KANA_MODE),
+        100 => Some(keys::CONVERT),
+        102 => Some(keys::NON_CONVERT),
+        130 => Some(keys::LANG1),
+        131 => Some(keys::LANG2),
+        _ => None,
+    }
+}
+
+/// 通过macOS按键码获取rdev没有的按键名，媒体键在macOS下不是按键事件
+#[cfg(target_os = "macos")]
+fn get_platform_key_name(keycode: u32) -> Option<&'static str> {
+    match keycode {
+        105 => Some(keys::F13),
+        107 => Some(keys::F14),
+        113 => Some(keys::F15),
+        106 => Some(keys::F16),
+        64 => Some(keys::F17),
+        79 => Some(keys::F18),
+        80 => Some(keys::F19),
+        90 => Some(keys::F20),
+        72 => Some(keys::VOLUME_UP),
+        73 => Some(keys::VOLUME_DOWN),
+        74 => Some(keys::VOLUME_MUTE),
+        110 => Some(keys::CONTEXT_MENU),
+        93 => Some(keys::INTL_YEN),
+        94 => Some(keys::INTL_RO),
+        102 => Some(keys::EISU),
+        104 => Some(keys::KANA_MODE),
+        _ => None,
+    }
+}
+
+/// F13~F24，index从0开始
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+fn get_extended_f_key(index: u32) -> &'static str {
+    [
+        keys::F13,
+        keys::F14,
+        keys::F15,
+        keys::F16,
+        keys::F17,
+        keys::F18,
+        keys::F19,
+        keys::F20,
+        keys::F21,
+        keys::F22,
+        keys::F23,
+        keys::F24,
+    ][index as usize]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use rdev::{Event, EventType, Key};
+    use tokio::sync::broadcast::{self, Receiver};
+
+    use super::*;
+
+    /// 模拟的事件序列，时间从固定的起点开始
+    struct Timeline {
+        handler: Handler,
+        receiver: Receiver<Message>,
+        start: SystemTime,
+    }
+
+    impl Timeline {
+        fn new() -> Self {
+            let (sender, receiver) = broadcast::channel(256);
+            Self {
+                handler: Handler::new(sender, KeySender::new()),
+                receiver,
+                start: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000),
+            }
+        }
+
+        fn send(&mut self, ms: u64, event_type: EventType) {
+            self.handler.handle(Event {
+                time: self.start + Duration::from_millis(ms),
+                name: None,
+                event_type,
+            });
+        }
+
+        fn press(&mut self, ms: u64, key: Key) {
+            self.send(ms, EventType::KeyPress(key));
+        }
+
+        fn release(&mut self, ms: u64, key: Key) {
+            self.send(ms, EventType::KeyRelease(key));
+        }
+
+        /// 取出目前为止发送的组合键消息
+        fn chords(&mut self) -> Vec<(Vec<String>, Vec<String>)> {
+            let mut chords = Vec::new();
+            while let Ok(message) = self.receiver.try_recv() {
+                if let MessageData::InputMessage(InputMessage {
+                    info:
+                        InputInfo::Chord {
+                            keys, modifiers, ..
+                        },
+                    ..
+                }) = message.data
+                {
+                    chords.push((keys, modifiers));
+                }
+            }
+            chords
+        }
+    }
+
+    fn chord(keys: &[&str], modifiers: &[&str]) -> (Vec<String>, Vec<String>) {
+        (
+            keys.iter().map(|key| key.to_string()).collect(),
+            modifiers.iter().map(|key| key.to_string()).collect(),
+        )
+    }
+
+    #[test]
+    fn chord_modifiers_are_sorted_regardless_of_press_order() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::ShiftLeft);
+        timeline.press(10, Key::ControlLeft);
+        timeline.press(20, Key::KeyS);
+        assert_eq!(
+            timeline.chords(),
+            vec![chord(
+                &[keys::KEY_S],
+                &[keys::MODIFIER_CTRL, keys::MODIFIER_SHIFT]
+            )]
+        );
+    }
+
+    #[test]
+    fn key_pressed_before_modifier_is_not_a_chord() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::KeyS);
+        timeline.press(10, Key::ControlLeft);
+        timeline.release(20, Key::KeyS);
+        timeline.release(30, Key::ControlLeft);
+        assert!(timeline.chords().is_empty());
+    }
+
+    #[test]
+    fn modifier_only_presses_are_not_chords() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::ControlLeft);
+        timeline.press(10, Key::ShiftLeft);
+        timeline.press(20, Key::Alt);
+        timeline.release(30, Key::Alt);
+        timeline.release(40, Key::ShiftLeft);
+        timeline.release(50, Key::ControlLeft);
+        assert!(timeline.chords().is_empty());
+    }
+
+    #[test]
+    fn left_and_right_modifiers_are_merged() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::ControlLeft);
+        timeline.press(10, Key::ControlRight);
+        timeline.press(20, Key::KeyA);
+        assert_eq!(
+            timeline.chords(),
+            vec![chord(&[keys::KEY_A], &[keys::MODIFIER_CTRL])]
+        );
+    }
+
+    #[test]
+    fn repeated_identical_chords_are_each_emitted() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::ControlLeft);
+        timeline.press(10, Key::KeyS);
+        timeline.release(20, Key::KeyS);
+        timeline.press(30, Key::KeyS);
+        timeline.release(40, Key::KeyS);
+        let expected = chord(&[keys::KEY_S], &[keys::MODIFIER_CTRL]);
+        assert_eq!(timeline.chords(), vec![expected.clone(), expected]);
+    }
+
+    #[test]
+    fn auto_repeat_does_not_emit_another_chord() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::ControlLeft);
+        timeline.press(10, Key::KeyS);
+        timeline.press(510, Key::KeyS);
+        timeline.press(540, Key::KeyS);
+        assert_eq!(timeline.chords().len(), 1);
+    }
+
+    #[test]
+    fn chord_has_no_time_window_while_modifier_is_held() {
+        // 组合键只看按下非修饰键时修饰键是否按住，与间隔无关
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::ControlLeft);
+        timeline.press(10_000, Key::KeyS);
+        assert_eq!(
+            timeline.chords(),
+            vec![chord(&[keys::KEY_S], &[keys::MODIFIER_CTRL])]
+        );
+    }
+
+    #[test]
+    fn released_modifier_is_not_part_of_chord() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::ControlLeft);
+        timeline.press(10, Key::ShiftLeft);
+        timeline.release(20, Key::ShiftLeft);
+        timeline.press(30, Key::KeyS);
+        timeline.release(40, Key::ControlLeft);
+        timeline.press(50, Key::KeyA);
+        assert_eq!(
+            timeline.chords(),
+            vec![chord(&[keys::KEY_S], &[keys::MODIFIER_CTRL])]
+        );
+    }
+
+    #[test]
+    fn chord_includes_all_held_keys() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::ControlLeft);
+        timeline.press(10, Key::KeyS);
+        timeline.press(20, Key::KeyA);
+        assert_eq!(
+            timeline.chords(),
+            vec![
+                chord(&[keys::KEY_S], &[keys::MODIFIER_CTRL]),
+                chord(&[keys::KEY_A, keys::KEY_S], &[keys::MODIFIER_CTRL]),
+            ]
+        );
+    }
+
+    #[test]
+    fn highlight_chord_strings_match_pressed_chords() {
+        let modifiers = vec![
+            keys::MODIFIER_CTRL.to_string(),
+            keys::MODIFIER_SHIFT.to_string(),
+        ];
+        let keys = vec![keys::KEY_S.to_string()];
+        assert!(is_same_chord("ctrl+shift+s", &modifiers, &keys));
+        assert!(is_same_chord(" Shift + Ctrl + S ", &modifiers, &keys));
+        assert!(is_same_chord("l_ctrl+r_shift+s", &modifiers, &keys));
+        assert!(!is_same_chord("ctrl+s", &modifiers, &keys));
+        assert!(!is_same_chord("ctrl+shift+a", &modifiers, &keys));
+        assert!(!is_same_chord("ctrl+shift", &modifiers, &keys));
+    }
+
+    /// 在ms时检查卡住的按键，返回仍按住的按键
+    fn expire(timeline: &mut Timeline, ms: u64, max_hold_ms: u64) -> Vec<String> {
+        let now = timeline.start + Duration::from_millis(ms);
+        timeline
+            .handler
+            .expire_keys(now, Duration::from_millis(max_hold_ms));
+        let mut pressing: Vec<String> = timeline
+            .handler
+            .pressing_keys
+            .keys()
+            .chain(timeline.handler.pressing_mouse_buttons.keys())
+            .cloned()
+            .collect();
+        pressing.sort();
+        pressing
+    }
+
+    #[test]
+    fn repeating_key_expires_when_repeats_stop() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::KeyA);
+        timeline.press(500, Key::KeyA);
+        timeline.press(533, Key::KeyA);
+        assert_eq!(expire(&mut timeline, 1_500, 1_000), vec![keys::KEY_A]);
+        assert_eq!(expire(&mut timeline, 1_534, 1_000), Vec::<String>::new());
+    }
+
+    #[test]
+    fn keys_without_repeats_expire_by_press_time() {
+        let mut timeline = Timeline::new();
+        // 按住不重复的修饰键、鼠标按键和还未开始重复的按键
+        timeline.press(0, Key::ShiftLeft);
+        timeline.send(0, EventType::ButtonPress(rdev::Button::Left));
+        timeline.press(100, Key::KeyA);
+        assert_eq!(expire(&mut timeline, 1_050, 1_000), vec![keys::KEY_A]);
+        assert_eq!(expire(&mut timeline, 1_101, 1_000), Vec::<String>::new());
+    }
+
+    #[test]
+    fn key_expires_after_it_stops_repeating() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::KeyW);
+        timeline.press(500, Key::KeyW);
+        // 按下D后系统只重复D，按住的W不会再重复
+        timeline.press(600, Key::KeyD);
+        timeline.press(1_100, Key::KeyD);
+        timeline.press(1_133, Key::KeyD);
+        assert_eq!(
+            expire(&mut timeline, 1_450, 1_000),
+            vec![keys::KEY_D, keys::KEY_W]
+        );
+        assert_eq!(expire(&mut timeline, 1_550, 1_000), vec![keys::KEY_D]);
+    }
+
+    #[test]
+    fn released_key_is_not_expired_later() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, Key::KeyA);
+        timeline.press(500, Key::KeyA);
+        timeline.release(600, Key::KeyA);
+        timeline.press(1_000, Key::KeyA);
+        // 重新按下后从新的按下时间计算
+        assert_eq!(expire(&mut timeline, 1_600, 1_000), vec![keys::KEY_A]);
+    }
+
+    #[test]
+    fn unknown_mouse_buttons_are_numbered() {
+        assert_eq!(
+            get_name(KeyButton::Button(Button::Unknown(2))),
+            keys::MOUSE_5
+        );
+        assert_eq!(get_name(KeyButton::Button(Button::Unknown(4))), "mouse_7");
+        assert_eq!(get_name(KeyButton::Button(Button::Unknown(9))), "mouse_12");
+    }
+
+    #[test]
+    fn repeats_are_sent_only_with_subscribers() {
+        let mut timeline = Timeline::new();
+        let repeats = |timeline: &mut Timeline| {
+            let mut repeats = Vec::new();
+            while let Ok(message) = timeline.receiver.try_recv() {
+                if let MessageData::InputMessage(InputMessage {
+                    info: InputInfo::Repeat { repeat, .. },
+                    ..
+                }) = message.data
+                {
+                    repeats.push(repeat);
+                }
+            }
+            repeats
+        };
+        timeline.press(0, Key::KeyA);
+        timeline.press(500, Key::KeyA);
+        assert_eq!(repeats(&mut timeline), Vec::<u64>::new());
+        let subscription = subscribe_repeat();
+        timeline.press(533, Key::KeyA);
+        assert_eq!(repeats(&mut timeline), vec![2]);
+        drop(subscription);
+        timeline.press(566, Key::KeyA);
+        assert_eq!(repeats(&mut timeline), Vec::<u64>::new());
     }
 }
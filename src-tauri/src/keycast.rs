@@ -0,0 +1,396 @@
+use std::time::{Duration, SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use crate::keys;
+
+/// 单个片段中最多保留的记号数
+const MAX_TOKENS: usize = 16;
+/// 隐私模式下代替输入字符的符号
+const MASK_CHAR: char = '•';
+
+/// 按键记录消息，客户端直接显示text即可
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyCastMessage {
+    /// 当前片段中的记号，如["Ctrl+C", "Esc ×3", "hello"]
+    pub tokens: Vec<String>,
+    /// 记号拼接后的文本
+    pub text: String,
+    /// 是否为新的片段(上一个片段已超时)
+    pub new_segment: bool,
+    /// 无新输入时片段的超时时间(ms)，客户端可在超时后清空文本
+    pub timeout_ms: u64,
+}
+
+/// 记号类型
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    /// 单个按键或组合键，重复按下时合并计数
+    Key,
+    /// 连续输入的字符
+    Word,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    text: String,
+    count: u32,
+}
+
+impl Token {
+    fn render(&self) -> String {
+        if self.count > 1 {
+            format!("{} ×{}", self.text, self.count)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+/// 按键记录聚合器，将按键按时间分段并合并为可读的记号
+pub struct KeyCast {
+    tokens: Vec<Token>,
+    last_time: Option<SystemTime>,
+}
+
+impl KeyCast {
+    pub fn new() -> Self {
+        Self {
+            tokens: Vec::new(),
+            last_time: None,
+        }
+    }
+
+    /// 处理一次键盘按下
+    /// name为按键名，label为按键输入的字符，modifiers为按住的修饰键(不区分左右)
+    /// show_text为false时输入的字符会被遮挡
+    /// 修饰键本身不产生记号，返回None
+    pub fn on_press(
+        &mut self,
+        name: &str,
+        label: Option<&str>,
+        modifiers: &Vec<String>,
+        time: SystemTime,
+        timeout_ms: u64,
+        show_text: bool,
+    ) -> Option<KeyCastMessage> {
+        if is_modifier(name) {
+            return None;
+        }
+        // 超时后开始新的片段
+        let new_segment = match self.last_time {
+            Some(last_time) => {
+                time.duration_since(last_time).unwrap_or(Duration::ZERO)
+                    >= Duration::from_millis(timeout_ms)
+            }
+            None => true,
+        };
+        if new_segment {
+            self.tokens.clear();
+        }
+        self.last_time = Some(time);
+
+        // 只按住shift时视为输入字符
+        let combo = modifiers
+            .iter()
+            .any(|modifier| !modifier.eq(keys::MODIFIER_SHIFT));
+        let typed = label
+            .filter(|label| label.chars().count() == 1)
+            .filter(|label| label.chars().all(|c| !c.is_control()));
+        match typed {
+            Some(text) if !combo && !name.eq(keys::SPACE) => {
+                let text = if show_text {
+                    text.to_string()
+                } else {
+                    MASK_CHAR.to_string()
+                };
+                self.push_word(&text);
+            }
+            // 输入单词时空格属于单词的一部分
+            _ if !combo && name.eq(keys::SPACE) && self.is_typing() => {
+                self.push_word(" ");
+            }
+            _ => {
+                let mut parts: Vec<String> = if combo {
+                    modifiers
+                        .iter()
+                        .map(|modifier| get_label(modifier))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+                parts.push(get_label(name));
+                self.push_key(parts.join("+"));
+            }
+        }
+        if self.tokens.len() > MAX_TOKENS {
+            self.tokens.drain(0..self.tokens.len() - MAX_TOKENS);
+        }
+
+        let tokens: Vec<String> = self
+            .tokens
+            .iter()
+            .map(|token| token.render().trim().to_string())
+            .collect();
+        Some(KeyCastMessage {
+            text: tokens.join("  "),
+            tokens,
+            new_segment,
+            timeout_ms,
+        })
+    }
+
+    /// 最后一个记号是否为正在输入的单词
+    fn is_typing(&self) -> bool {
+        match self.tokens.last() {
+            Some(token) => token.kind == TokenKind::Word,
+            None => false,
+        }
+    }
+
+    fn push_word(&mut self, text: &str) {
+        match self.tokens.last_mut() {
+            Some(token) if token.kind == TokenKind::Word => token.text.push_str(text),
+            _ => self.tokens.push(Token {
+                kind: TokenKind::Word,
+                text: text.to_string(),
+                count: 1,
+            }),
+        }
+    }
+
+    fn push_key(&mut self, text: String) {
+        match self.tokens.last_mut() {
+            Some(token) if token.kind == TokenKind::Key && token.text.eq(&text) => token.count += 1,
+            _ => self.tokens.push(Token {
+                kind: TokenKind::Key,
+                text,
+                count: 1,
+            }),
+        }
+    }
+}
+
+fn is_modifier(name: &str) -> bool {
+    [
+        keys::CONTROL_LEFT,
+        keys::CONTROL_RIGHT,
+        keys::SHIFT_LEFT,
+        keys::SHIFT_RIGHT,
+        keys::ALT_LEFT,
+        keys::ALT_RIGHT,
+        keys::META_LEFT,
+        keys::META_RIGHT,
+    ]
+    .contains(&name)
+}
+
+/// 获取按键或修饰键显示的文字
+pub fn get_label(name: &str) -> String {
+    let label = match name {
+        "ctrl" => "Ctrl",
+        "shift" => "Shift",
+        "alt" => "Alt",
+        "meta" => "Win",
+        "escape" => "Esc",
+        "enter" => "Enter",
+        "kp_enter" => "Enter",
+        "backspace" => "⌫",
+        "tab" => "Tab",
+        "space" => "Space",
+        "capslock" => "Caps",
+        "delete" | "kp_del" => "Del",
+        "insert" => "Ins",
+        "home" => "Home",
+        "end" => "End",
+        "pgup" => "PgUp",
+        "pgdn" => "PgDn",
+        "up_arrow" => "↑",
+        "down_arrow" => "↓",
+        "left_arrow" => "←",
+        "right_arrow" => "→",
+        "print_screen" => "PrtSc",
+        "scroll_lock" => "ScrLk",
+        "pause" => "Pause",
+        "num_lock" => "NumLk",
+        "function" => "Fn",
+        _ => {
+            // 数字小键盘按键显示为"Num 1"，其他按键首字母大写
+            if let Some(rest) = name.strip_prefix("kp_") {
+                return format!("Num {}", get_label(rest));
+            }
+            return name
+                .split('_')
+                .map(|part| {
+                    let mut chars = part.chars();
+                    match chars.next() {
+                        Some(first) => first.to_uppercase().chain(chars).collect(),
+                        None => String::new(),
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(" ");
+        }
+    };
+    label.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按时间依次输入按键，返回最后一条消息
+    struct Timeline {
+        key_cast: KeyCast,
+        start: SystemTime,
+        show_text: bool,
+    }
+
+    impl Timeline {
+        fn new() -> Self {
+            Self {
+                key_cast: KeyCast::new(),
+                start: SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000),
+                show_text: true,
+            }
+        }
+
+        fn press(
+            &mut self,
+            ms: u64,
+            name: &str,
+            label: Option<&str>,
+            modifiers: &[&str],
+        ) -> Option<KeyCastMessage> {
+            let modifiers = modifiers
+                .iter()
+                .map(|modifier| modifier.to_string())
+                .collect();
+            self.key_cast.on_press(
+                name,
+                label,
+                &modifiers,
+                self.start + Duration::from_millis(ms),
+                1000,
+                self.show_text,
+            )
+        }
+
+        /// 输入不按修饰键的字符
+        fn type_char(&mut self, ms: u64, c: &str) -> KeyCastMessage {
+            self.press(ms, c, Some(c), &[]).unwrap()
+        }
+    }
+
+    #[test]
+    fn typed_characters_form_a_word() {
+        let mut timeline = Timeline::new();
+        timeline.type_char(0, "h");
+        timeline.type_char(100, "i");
+        timeline.press(200, keys::SPACE, None, &[]);
+        let message = timeline.type_char(300, "a");
+        assert_eq!(message.tokens, vec!["hi a"]);
+        assert_eq!(message.text, "hi a");
+        assert!(!message.new_segment);
+    }
+
+    #[test]
+    fn repeated_keys_are_merged() {
+        let mut timeline = Timeline::new();
+        timeline.press(0, keys::ESCAPE, None, &[]);
+        timeline.press(100, keys::ESCAPE, None, &[]);
+        let message = timeline.press(200, keys::ESCAPE, None, &[]).unwrap();
+        assert_eq!(message.tokens, vec!["Esc ×3"]);
+        let message = timeline.press(300, keys::RETURN, None, &[]).unwrap();
+        assert_eq!(message.text, "Esc ×3  Enter");
+    }
+
+    #[test]
+    fn combos_are_joined_with_modifiers() {
+        let mut timeline = Timeline::new();
+        timeline.type_char(0, "a");
+        let message = timeline
+            .press(
+                100,
+                "c",
+                Some("c"),
+                &[keys::MODIFIER_CTRL, keys::MODIFIER_SHIFT],
+            )
+            .unwrap();
+        assert_eq!(message.tokens, vec!["a", "Ctrl+Shift+C"]);
+        // 只按住Shift时仍是输入字符
+        let message = timeline
+            .press(200, "b", Some("B"), &[keys::MODIFIER_SHIFT])
+            .unwrap();
+        assert_eq!(message.tokens, vec!["a", "Ctrl+Shift+C", "B"]);
+    }
+
+    #[test]
+    fn modifiers_alone_produce_nothing() {
+        let mut timeline = Timeline::new();
+        assert!(timeline.press(0, keys::CONTROL_LEFT, None, &[]).is_none());
+        assert!(timeline
+            .press(10, keys::SHIFT_RIGHT, None, &[keys::MODIFIER_CTRL])
+            .is_none());
+    }
+
+    #[test]
+    fn space_outside_a_word_is_a_key() {
+        let mut timeline = Timeline::new();
+        let message = timeline.press(0, keys::SPACE, Some(" "), &[]).unwrap();
+        assert_eq!(message.tokens, vec!["Space"]);
+    }
+
+    #[test]
+    fn timeout_starts_a_new_segment() {
+        let mut timeline = Timeline::new();
+        let message = timeline.type_char(0, "a");
+        assert!(message.new_segment);
+        let message = timeline.type_char(999, "b");
+        assert!(!message.new_segment);
+        let message = timeline.type_char(1999, "c");
+        assert!(message.new_segment);
+        assert_eq!(message.tokens, vec!["c"]);
+    }
+
+    #[test]
+    fn hidden_text_is_masked() {
+        let mut timeline = Timeline::new();
+        timeline.show_text = false;
+        timeline.type_char(0, "p");
+        let message = timeline.type_char(100, "w");
+        assert_eq!(message.text, "••");
+        // 组合键不遮挡
+        let message = timeline
+            .press(200, "v", Some("v"), &[keys::MODIFIER_CTRL])
+            .unwrap();
+        assert_eq!(message.tokens, vec!["••", "Ctrl+V"]);
+    }
+
+    #[test]
+    fn only_recent_tokens_are_kept() {
+        let mut timeline = Timeline::new();
+        let mut message = None;
+        for i in 0..MAX_TOKENS as u64 + 4 {
+            // 交替输入不同按键，避免合并
+            let name = if i % 2 == 0 {
+                keys::ESCAPE
+            } else {
+                keys::RETURN
+            };
+            message = timeline.press(i * 10, name, None, &[]);
+        }
+        let message = message.unwrap();
+        assert_eq!(message.tokens.len(), MAX_TOKENS);
+        assert_eq!(message.tokens.last().unwrap(), "Enter");
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(get_label(keys::MODIFIER_META), "Win");
+        assert_eq!(get_label("kp_1"), "Num 1");
+        assert_eq!(get_label("kp_enter"), "Enter");
+        assert_eq!(get_label("volume_up"), "Volume Up");
+    }
+}
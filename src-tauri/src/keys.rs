@@ -77,7 +77,7 @@ pub static KEY_L: &str = "l";
 pub static SEMICOLON: &str = ";";
 pub static QUOTE: &str = "'";
 pub static BACKSLASH: &str = "\\";
-pub static INTLBACKSLASH: &str = "";
+pub static INTLBACKSLASH: &str = "intl_backslash";
 pub static KEY_Z: &str = "z";
 pub static KEY_X: &str = "x";
 pub static KEY_C: &str = "c";
@@ -107,6 +107,51 @@ pub static KP_9: &str = "kp_9";
 pub static KP_DELETE: &str = "kp_del";
 pub static FUNCTION: &str = "function";
 
+// 扩展按键，rdev中为Key::Unknown，按平台的按键码转换
+pub static F13: &str = "f13";
+pub static F14: &str = "f14";
+pub static F15: &str = "f15";
+pub static F16: &str = "f16";
+pub static F17: &str = "f17";
+pub static F18: &str = "f18";
+pub static F19: &str = "f19";
+pub static F20: &str = "f20";
+pub static F21: &str = "f21";
+pub static F22: &str = "f22";
+pub static F23: &str = "f23";
+pub static F24: &str = "f24";
+pub static CONTEXT_MENU: &str = "context_menu";
+pub static MEDIA_PLAY_PAUSE: &str = "media_play_pause";
+pub static MEDIA_STOP: &str = "media_stop";
+pub static MEDIA_NEXT: &str = "media_next";
+pub static MEDIA_PREV: &str = "media_prev";
+pub static VOLUME_MUTE: &str = "volume_mute";
+pub static VOLUME_DOWN: &str = "volume_down";
+pub static VOLUME_UP: &str = "volume_up";
+pub static BROWSER_BACK: &str = "browser_back";
+pub static BROWSER_FORWARD: &str = "browser_forward";
+pub static BROWSER_REFRESH: &str = "browser_refresh";
+pub static BROWSER_STOP: &str = "browser_stop";
+pub static BROWSER_SEARCH: &str = "browser_search";
+pub static BROWSER_FAVORITES: &str = "browser_favorites";
+pub static BROWSER_HOME: &str = "browser_home";
+pub static LAUNCH_MAIL: &str = "launch_mail";
+pub static LAUNCH_MEDIA: &str = "launch_media";
+pub static LAUNCH_APP1: &str = "launch_app1";
+pub static LAUNCH_APP2: &str = "launch_app2";
+// 日文、韩文键盘
+pub static INTL_RO: &str = "intl_ro";
+pub static INTL_YEN: &str = "intl_yen";
+pub static KANA_MODE: &str = "kana_mode";
+pub static CONVERT: &str = "convert";
+pub static NON_CONVERT: &str = "non_convert";
+pub static EISU: &str = "eisu";
+pub static LANG1: &str = "lang1";
+pub static LANG2: &str = "lang2";
+pub static OEM_8: &str = "oem_8";
+/// 没有名字的按键以"unknown_<按键码>"发送，按键码与平台有关
+pub static UNKNOWN_PREFIX: &str = "unknown_";
+
 // 鼠标按键
 pub static MOUSE_LEFT: &str = "mouse_1";
 pub static MOUSE_RIGHT: &str = "mouse_2";
@@ -114,3 +159,11 @@ pub static MOUSE_MIDDLE: &str = "mouse_3";
 pub static MOUSE_4: &str = "mouse_4";
 pub static MOUSE_5: &str = "mouse_5";
 pub static MOUSE_6: &str = "mouse_6";
+/// 其他鼠标按键以"mouse_<序号>"发送，侧键之后的按键从7开始
+pub static MOUSE_PREFIX: &str = "mouse_";
+
+// 修饰键(不区分左右)，用于组合键
+pub static MODIFIER_CTRL: &str = "ctrl";
+pub static MODIFIER_SHIFT: &str = "shift";
+pub static MODIFIER_ALT: &str = "alt";
+pub static MODIFIER_META: &str = "meta";
@@ -0,0 +1,254 @@
+use rdev::Key;
+
+#[derive(Debug)]
+pub enum LayoutError {
+    /// 当前平台或会话无法读取键盘布局
+    UnavailableError,
+}
+
+/// 获取按键在当前键盘布局下不按修饰键时的字符，与Shift、AltGr等修饰键无关
+/// 按键没有字符时返回None，无法读取键盘布局时返回错误
+#[cfg(target_os = "linux")]
+pub fn get_label(key: Key) -> Result<Option<String>, LayoutError> {
+    x11_layout::get_label(key)
+}
+
+#[cfg(target_os = "windows")]
+pub fn get_label(key: Key) -> Result<Option<String>, LayoutError> {
+    windows_layout::get_label(key)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub fn get_label(_key: Key) -> Result<Option<String>, LayoutError> {
+    Err(LayoutError::UnavailableError)
+}
+
+/// 只保留单个可见字符并转为小写
+fn to_label(c: char) -> Option<String> {
+    if c.is_control() || c.is_whitespace() {
+        return None;
+    }
+    Some(c.to_lowercase().collect())
+}
+
+/// 通过XKB读取当前布局组第一层(不按修饰键)的keysym
+#[cfg(target_os = "linux")]
+mod x11_layout {
+    use std::{mem, os::raw::c_ulong, ptr, sync::Mutex};
+
+    use once_cell::sync::Lazy;
+    use rdev::Key;
+    use x11::xlib;
+
+    use super::{to_label, LayoutError};
+    use crate::evdev_backend;
+
+    /// XkbUseCoreKbd
+    const XKB_USE_CORE_KBD: u32 = 0x0100;
+
+    struct KeymapDisplay(*mut xlib::Display);
+
+    // display只在持有锁时使用
+    unsafe impl Send for KeymapDisplay {}
+
+    /// 用于读取键盘布局的连接，打开失败(Wayland下没有XWayland等)时为None
+    static DISPLAY: Lazy<Mutex<Option<KeymapDisplay>>> = Lazy::new(|| {
+        let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
+        Mutex::new(if display.is_null() {
+            None
+        } else {
+            Some(KeymapDisplay(display))
+        })
+    });
+
+    pub fn get_label(key: Key) -> Result<Option<String>, LayoutError> {
+        let display = DISPLAY.lock().unwrap();
+        let display = display.as_ref().ok_or(LayoutError::UnavailableError)?.0;
+        let keycode = match get_keycode(key) {
+            Some(keycode) => keycode,
+            None => return Ok(None),
+        };
+        unsafe {
+            // 多个布局时使用当前的布局组，请求时也会处理布局变化的通知
+            let mut state: xlib::XkbStateRec = mem::zeroed();
+            let group = if xlib::XkbGetState(display, XKB_USE_CORE_KBD, &mut state) == 0 {
+                state.group as i32
+            } else {
+                0
+            };
+            let keysym = xlib::XkbKeycodeToKeysym(display, keycode, group, 0);
+            Ok(keysym_to_char(keysym).and_then(to_label))
+        }
+    }
+
+    /// rdev在X11下的按键对应的X按键码，即evdev按键码+8
+    fn get_keycode(key: Key) -> Option<u8> {
+        if let Key::Unknown(keycode) = key {
+            return u8::try_from(keycode).ok();
+        }
+        (1..=247u16)
+            .find(|code| evdev_backend::key_from_code(*code) == key)
+            .map(|code| (code + 8) as u8)
+    }
+
+    /// keysym对应的字符，Latin-1的keysym与字符相同，Unicode的keysym为0x01000000+码位
+    /// 其他旧的keysym(如部分西里尔字母)返回None
+    fn keysym_to_char(keysym: c_ulong) -> Option<char> {
+        match keysym {
+            0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym as u32),
+            0x0100_0100..=0x0110_ffff => char::from_u32((keysym - 0x0100_0000) as u32),
+            _ => None,
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn latin1_keysyms() {
+            assert_eq!(keysym_to_char(0x61), Some('a'));
+            assert_eq!(keysym_to_char(0x31), Some('1'));
+            // XK_odiaeresis
+            assert_eq!(keysym_to_char(0xf6), Some('ö'));
+        }
+
+        #[test]
+        fn unicode_keysyms() {
+            // 俄语布局的ф
+            assert_eq!(keysym_to_char(0x0100_0444), Some('ф'));
+        }
+
+        #[test]
+        fn non_character_keysyms() {
+            // XK_Return、XK_F1、XK_dead_acute
+            assert_eq!(keysym_to_char(0xff0d), None);
+            assert_eq!(keysym_to_char(0xffbe), None);
+            assert_eq!(keysym_to_char(0xfe51), None);
+        }
+
+        #[test]
+        fn keycodes() {
+            assert_eq!(get_keycode(Key::KeyA), Some(38));
+            assert_eq!(get_keycode(Key::Num1), Some(10));
+            assert_eq!(get_keycode(Key::IntlBackslash), Some(94));
+            assert_eq!(get_keycode(Key::Unknown(191)), Some(191));
+        }
+    }
+}
+
+/// 通过ToUnicodeEx读取焦点窗口的键盘布局中不按修饰键时的字符
+#[cfg(target_os = "windows")]
+mod windows_layout {
+    use rdev::Key;
+
+    use super::{to_label, LayoutError};
+
+    /// 不改变系统的死键状态(Windows 10 1607起)
+    const TO_UNICODE_NO_STATE: u32 = 0x4;
+    /// MAPVK_VK_TO_VSC
+    const MAPVK_VK_TO_VSC: u32 = 0;
+
+    #[link(name = "user32")]
+    extern "system" {
+        fn GetForegroundWindow() -> isize;
+        fn GetWindowThreadProcessId(window: isize, process_id: *mut u32) -> u32;
+        fn GetKeyboardLayout(thread_id: u32) -> isize;
+        fn MapVirtualKeyExW(code: u32, map_type: u32, layout: isize) -> u32;
+        fn ToUnicodeEx(
+            virtual_key: u32,
+            scan_code: u32,
+            key_state: *const u8,
+            buffer: *mut u16,
+            buffer_len: i32,
+            flags: u32,
+            layout: isize,
+        ) -> i32;
+    }
+
+    pub fn get_label(key: Key) -> Result<Option<String>, LayoutError> {
+        let virtual_key = match get_virtual_key(key) {
+            Some(virtual_key) => virtual_key,
+            None => return Ok(None),
+        };
+        unsafe {
+            let thread = GetWindowThreadProcessId(GetForegroundWindow(), std::ptr::null_mut());
+            let layout = GetKeyboardLayout(thread);
+            let scan_code = MapVirtualKeyExW(virtual_key, MAPVK_VK_TO_VSC, layout);
+            // 所有按键都未按下，即第一层的字符
+            let key_state = [0u8; 256];
+            let mut buffer = [0u16; 8];
+            let len = ToUnicodeEx(
+                virtual_key,
+                scan_code,
+                key_state.as_ptr(),
+                buffer.as_mut_ptr(),
+                buffer.len() as i32,
+                TO_UNICODE_NO_STATE,
+                layout,
+            );
+            // 死键返回-1，不显示
+            if len != 1 {
+                return Ok(None);
+            }
+            Ok(char::from_u32(buffer[0] as u32).and_then(to_label))
+        }
+    }
+
+    /// 有字符的按键对应的虚拟键码
+    fn get_virtual_key(key: Key) -> Option<u32> {
+        let virtual_key = match key {
+            Key::KeyA => 0x41,
+            Key::KeyB => 0x42,
+            Key::KeyC => 0x43,
+            Key::KeyD => 0x44,
+            Key::KeyE => 0x45,
+            Key::KeyF => 0x46,
+            Key::KeyG => 0x47,
+            Key::KeyH => 0x48,
+            Key::KeyI => 0x49,
+            Key::KeyJ => 0x4a,
+            Key::KeyK => 0x4b,
+            Key::KeyL => 0x4c,
+            Key::KeyM => 0x4d,
+            Key::KeyN => 0x4e,
+            Key::KeyO => 0x4f,
+            Key::KeyP => 0x50,
+            Key::KeyQ => 0x51,
+            Key::KeyR => 0x52,
+            Key::KeyS => 0x53,
+            Key::KeyT => 0x54,
+            Key::KeyU => 0x55,
+            Key::KeyV => 0x56,
+            Key::KeyW => 0x57,
+            Key::KeyX => 0x58,
+            Key::KeyY => 0x59,
+            Key::KeyZ => 0x5a,
+            Key::Num0 => 0x30,
+            Key::Num1 => 0x31,
+            Key::Num2 => 0x32,
+            Key::Num3 => 0x33,
+            Key::Num4 => 0x34,
+            Key::Num5 => 0x35,
+            Key::Num6 => 0x36,
+            Key::Num7 => 0x37,
+            Key::Num8 => 0x38,
+            Key::Num9 => 0x39,
+            Key::SemiColon => 0xba,
+            Key::Equal => 0xbb,
+            Key::Comma => 0xbc,
+            Key::Minus => 0xbd,
+            Key::Dot => 0xbe,
+            Key::Slash => 0xbf,
+            Key::BackQuote => 0xc0,
+            Key::LeftBracket => 0xdb,
+            Key::BackSlash => 0xdc,
+            Key::RightBracket => 0xdd,
+            Key::Quote => 0xde,
+            Key::IntlBackslash => 0xe2,
+            Key::Unknown(virtual_key) => virtual_key,
+            _ => return None,
+        };
+        Some(virtual_key)
+    }
+}
@@ -3,9 +3,7 @@
 
 use std::{
     ffi::OsString,
-    fs::{self, DirEntry},
-    hash,
-    io::Error,
+    fs, hash,
     path::{Path, PathBuf},
     sync::Mutex,
     thread,
@@ -13,10 +11,10 @@ use std::{
 };
 
 use config::Config;
-use file::{get_dir_entries, FileError};
 use inputs::{start, InputInfo, InputMessage, InputSource};
 use message::{Message, MessageData, MessageType};
 use once_cell::sync::{Lazy, OnceCell};
+use preset::PresetDefaults;
 use rdev::{listen, Event};
 use serde_json::Value;
 use tauri::{
@@ -32,13 +30,35 @@ use tokio::{
 };
 use window_shadows::set_shadow;
 
+#[cfg(any(target_os = "windows", test))]
+mod altgr;
+mod cli;
 mod config;
 mod constants;
+mod coverage;
+#[cfg(target_os = "linux")]
+mod evdev_backend;
 mod file;
+mod focus;
+mod gamepad;
+mod generator;
+mod gesture;
+mod inject;
 mod inputs;
+mod keycast;
 mod keys;
+mod layout;
 mod message;
+mod monitor;
+mod mouse;
+mod preset;
+mod record;
 mod server;
+mod stats;
+mod subtitles;
+mod thumbnail;
+mod usage;
+mod watchdog;
 
 /// 设置
 // static CONFIG: Lazy<Mutex<Config>> = Lazy::new(|| {
@@ -53,17 +73,7 @@ mod server;
 //     })
 // });
 
-static mut CONFIG: Lazy<Mutex<Config>> = Lazy::new(|| {
-    Mutex::from(Config {
-        preset: None,
-        enable: true,
-        key_down_transition_duration: 0,
-        key_up_transition_duration: 100,
-        mouse_move_enable: true,
-        mouse_move_radius_px: 50,
-        mouse_move_transition_duration: 100,
-    })
-});
+static mut CONFIG: Lazy<Mutex<Config>> = Lazy::new(|| Mutex::from(Config::default()));
 
 static mut NOTIFIED: Lazy<Mutex<bool>> = Lazy::new(|| Mutex::from(false));
 
@@ -72,6 +82,17 @@ struct Version(String);
 
 #[tokio::main]
 async fn main() {
+    let context = tauri::generate_context!();
+    // 内置预设目录，命令行中检查预设名时也需要
+    preset::set_resource_dir(
+        tauri::api::path::resource_dir(context.package_info(), &tauri::Env::default())
+            .map(|dir| dir.join(constants::PRESETS_RESOURCE_DIR)),
+    );
+    // 命令行调用，执行后直接退出
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = cli::run(&args).await {
+        std::process::exit(code);
+    }
     let version = Version(env!("CARGO_PKG_VERSION").to_string());
     // 初始化配置
     initialize_config();
@@ -81,6 +102,23 @@ async fn main() {
     let message_sender_config = message_sender.clone();
     // 输入发送器
     let message_sender_input = message_sender.clone();
+    // 统计数据发送器
+    let message_sender_stats = message_sender.clone();
+    // 手柄输入发送器
+    let message_sender_gamepad = message_sender.clone();
+    // 显示器变化发送器
+    let message_sender_monitor = message_sender.clone();
+    // 按键和按钮按下、松开消息的通道，不会丢弃消息
+    let key_sender = message::KeySender::new();
+    let key_sender_config = key_sender.clone();
+    let key_sender_input = key_sender.clone();
+    let key_sender_gamepad = key_sender.clone();
+    // 鼠标移动广播通道
+    let (motion_sender, _) = broadcast::channel(256);
+    let motion_sender = mouse::MotionSender(motion_sender);
+    let motion_sender_config = motion_sender.clone();
+    let motion_sender_mouse = motion_sender.clone();
+    let motion_sender_gamepad = motion_sender.clone();
 
     // 初始化端口
     let mut port = 61477;
@@ -93,11 +131,33 @@ async fn main() {
     }
 
     // 服务器task
-    let _server_task = tokio::task::spawn(server::run(message_sender, port));
+    let _server_task = tokio::task::spawn(server::run(
+        message_sender,
+        key_sender,
+        motion_sender,
+        port,
+    ));
+
+    // 鼠标移动合并发送task
+    let _mouse_task = tokio::task::spawn(mouse::run(motion_sender_mouse));
+
+    // 显示器变化检查task
+    let _monitor_task = tokio::task::spawn(monitor::run(message_sender_monitor));
+
+    // 统计数据广播task
+    let _stats_task = tokio::task::spawn(stats::run(message_sender_stats));
+
+    // 按键次数保存task
+    let _usage_task = tokio::task::spawn(usage::run());
 
     // 按键监听task
     let _input = tokio::task::spawn_blocking(move || {
-        start(message_sender_input);
+        start(message_sender_input, key_sender_input);
+    });
+
+    // 手柄监听task
+    let _gamepad = tokio::task::spawn_blocking(move || {
+        gamepad::start(message_sender_gamepad, key_sender_gamepad, motion_sender_gamepad);
     });
 
     // 系统托盘图标
@@ -107,10 +167,36 @@ async fn main() {
     tauri::Builder::default()
         // 发送器，用于在设置改变后进行广播
         .manage(message_sender_config)
+        .manage(key_sender_config)
+        .manage(motion_sender_config)
         .manage(port)
         .manage(version)
         .invoke_handler(tauri::generate_handler![
-            get_presets,
+            preset::get_presets,
+            preset::get_preset_infos,
+            preset::get_preset,
+            preset::get_resolved_preset,
+            preset::save_preset,
+            preset::create_preset_from_image,
+            preset::duplicate_preset,
+            preset::delete_preset,
+            preset::capture_key,
+            generator::generate_preset,
+            usage::get_usage,
+            usage::reset_usage,
+            usage::export_heatmap,
+            record::start_input_recording,
+            record::stop_input_recording,
+            record::start_input_replay,
+            record::step_input_replay,
+            record::stop_input_replay,
+            subtitles::export_subtitles,
+            monitor::get_monitor_list,
+            inputs::get_input_devices,
+            inject::inject_input,
+            inject::inject_input_script,
+            coverage::get_preset_coverage,
+            coverage::reset_preset_coverage,
             set_config,
             get_config,
             get_port,
@@ -126,6 +212,7 @@ async fn main() {
         .on_system_tray_event(|app, event| match event {
             tauri::SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
                 "quit" => {
+                    usage::save();
                     std::process::exit(0);
                 }
                 "setting" => {
@@ -148,7 +235,7 @@ async fn main() {
 
             Ok(())
         })
-        .build(tauri::generate_context!())
+        .build(context)
         .expect("error while running tauri application")
         .run(|_app_handle, event| match event {
             tauri::RunEvent::ExitRequested { api, .. } => {
@@ -180,15 +267,7 @@ fn initialize_config() -> () {
 // 从设置文件读取设置
 fn get_config_from_file() -> Config {
     // 初始化config
-    let mut config = Config {
-        preset: None,
-        enable: true,
-        key_down_transition_duration: 0,
-        key_up_transition_duration: 100,
-        mouse_move_enable: true,
-        mouse_move_radius_px: 50,
-        mouse_move_transition_duration: 100,
-    };
+    let mut config = Config::default();
     // 获取设置文件
     let path = Path::new("./config.json");
     if !path.exists() {
@@ -262,7 +341,14 @@ fn set_config(new_config: Value, state: State<Sender<Message>>) -> bool {
         eprintln!("{:?}", error);
         return false;
     }
-    let config = config_res.unwrap();
+    let mut config: Config = config_res.unwrap();
+    // 切换预设后应用预设推荐的设置
+    let old_preset = unsafe { CONFIG.lock().unwrap().preset.clone() };
+    if config.preset != old_preset {
+        let old_defaults = get_preset_defaults(&old_preset);
+        let new_defaults = get_preset_defaults(&config.preset);
+        config.apply_preset_defaults(&old_defaults, &new_defaults);
+    }
     if save_config(&config) {
         unsafe {
             CONFIG.lock().unwrap().set(config);
@@ -279,6 +365,15 @@ fn set_config(new_config: Value, state: State<Sender<Message>>) -> bool {
     }
 }
 
+/// 获取预设推荐的设置，预设不存在时为空
+fn get_preset_defaults(preset: &Option<String>) -> PresetDefaults {
+    preset
+        .as_ref()
+        .and_then(|name| preset::resolve_manifest(name).ok())
+        .map(|manifest| manifest.defaults)
+        .unwrap_or_default()
+}
+
 /// 保存设置
 fn save_config(config: &Config) -> bool {
     let path = Path::new("./config.json");
@@ -288,99 +383,3 @@ fn save_config(config: &Config) -> bool {
     };
     true
 }
-
-#[derive(Debug)]
-enum PresetError {
-    ReadFilesError,
-    FileTypeError,
-    NotDirError,
-    NotPresetError,
-}
-
-/// 读取预设列表
-#[tauri::command]
-fn get_presets(handle: tauri::AppHandle) -> Vec<String> {
-    // 初始化预设数组
-    let mut preset_list: Vec<String> = Vec::new();
-    // 获取路径
-    let path_buf = handle.path_resolver().resolve_resource("presets");
-    // 获取文件夹文件
-    let read_dir = get_dir_entries(path_buf);
-    if let Err(_) = read_dir {
-        return preset_list;
-    }
-    // 遍历文件入口
-    for entry_res in read_dir.unwrap() {
-        let preset_res = get_single_preset(entry_res);
-        if let Err(_) = preset_res {
-            continue;
-        }
-        preset_list.push(preset_res.unwrap());
-    }
-    preset_list
-}
-
-/// 读取并判断文件夹是否为单个预设文件夹
-fn get_single_preset(entry_res: Result<DirEntry, Error>) -> Result<String, PresetError> {
-    if let Err(_) = entry_res {
-        return Err(PresetError::ReadFilesError);
-    }
-    let entry = entry_res.unwrap();
-    // 读取文件类型
-    let file_type_res = entry.file_type();
-    if let Err(_) = file_type_res {
-        return Err(PresetError::FileTypeError);
-    }
-    // 是否为文件夹
-    if !file_type_res.unwrap().is_dir() {
-        return Err(PresetError::NotDirError);
-    }
-    // 读取文件夹
-    let file_path = entry.path();
-    let read_dir_res = get_dir_entries(Some(file_path));
-    if let Err(_) = read_dir_res {
-        return Err(PresetError::ReadFilesError);
-    }
-    // 判断文件
-    let mut has_image = false;
-    let mut has_config = false;
-    for entry_res in read_dir_res.unwrap() {
-        if let Err(_) = entry_res {
-            continue;
-        }
-        // 判断文件名
-        let file_name = get_file_name(entry_res.unwrap()).unwrap_or("".to_string());
-        if file_name.eq("") {
-            continue;
-        } else if file_name.eq(constants::PRESET_IMAGE_FILE_NAME) {
-            has_image = true;
-        } else if file_name.eq(constants::PRESET_CONFIG_FILE_NAME) {
-            has_config = true;
-        };
-        // 图片和配置文件都存在则
-        if has_image && has_config {
-            return if let Some(str) = entry.file_name().to_str() {
-                Ok(str.to_string())
-            } else {
-                Err(PresetError::ReadFilesError)
-            };
-        }
-    }
-    Err(PresetError::NotPresetError)
-}
-
-/// 获取文件名，如果不是文件或者其他则返回错误
-fn get_file_name(entry: DirEntry) -> Result<String, FileError> {
-    let file_type_res = entry.file_type();
-    if let Err(_) = file_type_res {
-        return Err(FileError::FileTypeError);
-    }
-    if file_type_res.unwrap().is_file() {
-        match entry.file_name().to_str() {
-            Some(str) => Ok(str.to_string()),
-            None => Err(FileError::ReadDirError),
-        }
-    } else {
-        return Err(FileError::NotFileError);
-    }
-}
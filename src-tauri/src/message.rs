@@ -1,12 +1,23 @@
+use std::sync::{Arc, Mutex};
+
 use serde::{Serialize, Deserialize};
+use tokio::sync::mpsc::{self, Receiver, Sender};
 
-use crate::{config::Config, inputs::InputMessage};
+use crate::{
+    config::Config, focus::FocusMessage, inputs::InputMessage, keycast::KeyCastMessage, monitor::Monitor,
+    stats::StatsMessage,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum MessageType {
     Input,
     Config,
+    KeyCast,
+    Stats,
+    Monitors,
+    Paused,
+    Resumed,
     Test
 }
 
@@ -17,10 +28,74 @@ pub struct Message {
     pub data: MessageData,
 }
 
+/// 每个订阅者的队列长度
+const KEY_QUEUE_CAPACITY: usize = 4096;
+
+/// 按键和按钮按下、松开消息的发送器
+/// 每个订阅者有自己的队列，消息不会像广播通道一样在接收慢时被跳过
+/// 队列满时移除该订阅者，接收端随之结束，由客户端重新连接
+#[derive(Debug, Clone, Default)]
+pub struct KeySender(Arc<Mutex<Vec<Sender<Message>>>>);
+
+impl KeySender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self) -> Receiver<Message> {
+        let (sender, receiver) = mpsc::channel(KEY_QUEUE_CAPACITY);
+        self.0.lock().unwrap().push(sender);
+        receiver
+    }
+
+    /// 发送给所有订阅者，接收端已关闭或队列已满的订阅者会被移除
+    pub fn send(&self, message: Message) {
+        self.0
+            .lock()
+            .unwrap()
+            .retain(|sender| sender.try_send(message.clone()).is_ok());
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum MessageData {
     ConfigMessage(Config),
     InputMessage(InputMessage),
+    KeyCastMessage(KeyCastMessage),
+    StatsMessage(StatsMessage),
+    MonitorsMessage(Vec<Monitor>),
+    FocusMessage(FocusMessage),
     TestMessage(String),
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_message(text: &str) -> Message {
+        Message {
+            r#type: MessageType::Test,
+            data: MessageData::TestMessage(text.to_string()),
+        }
+    }
+
+    #[test]
+    fn full_subscriber_is_dropped() {
+        let key_sender = KeySender::new();
+        let mut slow = key_sender.subscribe();
+        let mut fast = key_sender.subscribe();
+        for _ in 0..KEY_QUEUE_CAPACITY {
+            key_sender.send(test_message("key"));
+            assert!(fast.try_recv().is_ok());
+        }
+        // 队列满后移除，已在队列中的消息仍可以读取，之后接收端结束
+        key_sender.send(test_message("overflow"));
+        assert_eq!(key_sender.0.lock().unwrap().len(), 1);
+        assert!(fast.try_recv().is_ok());
+        for _ in 0..KEY_QUEUE_CAPACITY {
+            assert!(slow.try_recv().is_ok());
+        }
+        assert_eq!(slow.try_recv().unwrap_err(), mpsc::error::TryRecvError::Disconnected);
+    }
+}
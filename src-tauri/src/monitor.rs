@@ -0,0 +1,112 @@
+use std::{sync::Mutex, time::Duration};
+
+use display_info::DisplayInfo;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::Sender;
+
+use crate::message::{Message, MessageData, MessageType};
+
+/// 检查显示器变化的间隔
+const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// 显示器信息，坐标为全局桌面坐标
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Monitor {
+    /// 按位置(从左到右、从上到下)排列的序号
+    pub index: usize,
+    pub id: u32,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+impl Monitor {
+    fn contains(&self, x: f64, y: f64) -> bool {
+        x >= self.x as f64
+            && y >= self.y as f64
+            && x < self.x as f64 + self.width as f64
+            && y < self.y as f64 + self.height as f64
+    }
+}
+
+static MONITORS: Lazy<Mutex<Vec<Monitor>>> = Lazy::new(|| Mutex::new(read_monitors()));
+
+/// 读取所有显示器，读取失败时返回空列表
+fn read_monitors() -> Vec<Monitor> {
+    let mut displays = match DisplayInfo::all() {
+        Ok(displays) => displays,
+        Err(error) => {
+            eprintln!("monitor error: {:?}", error);
+            return Vec::new();
+        }
+    };
+    displays.sort_by_key(|display| (display.x, display.y));
+    displays
+        .iter()
+        .enumerate()
+        .map(|(index, display)| Monitor {
+            index,
+            id: display.id,
+            x: display.x,
+            y: display.y,
+            width: display.width,
+            height: display.height,
+            scale_factor: display.scale_factor,
+            is_primary: display.is_primary,
+        })
+        .collect()
+}
+
+/// 获取当前的显示器列表
+pub fn get_monitors() -> Vec<Monitor> {
+    MONITORS.lock().unwrap().clone()
+}
+
+/// 获取坐标所在的显示器和在该显示器内归一化(0~1)的坐标
+pub fn locate(x: f64, y: f64) -> Option<(Monitor, (f64, f64))> {
+    let monitors = MONITORS.lock().unwrap();
+    let monitor = monitors.iter().find(|monitor| monitor.contains(x, y))?;
+    let local = (
+        (x - monitor.x as f64) / monitor.width as f64,
+        (y - monitor.y as f64) / monitor.height as f64,
+    );
+    Some((monitor.clone(), local))
+}
+
+/// 重新读取显示器，有变化时返回新的列表
+fn refresh() -> Option<Vec<Monitor>> {
+    let monitors = read_monitors();
+    // 读取失败时保留之前的显示器
+    if monitors.is_empty() {
+        return None;
+    }
+    let mut current = MONITORS.lock().unwrap();
+    if current.eq(&monitors) {
+        return None;
+    }
+    *current = monitors.clone();
+    Some(monitors)
+}
+
+/// 定时检查显示器变化(分辨率、排列、插拔)，有变化时广播新的显示器列表
+pub async fn run(sender: Sender<Message>) {
+    loop {
+        tokio::time::sleep(REFRESH_INTERVAL).await;
+        if let Ok(Some(monitors)) = tokio::task::spawn_blocking(refresh).await {
+            let _ = sender.send(Message {
+                r#type: MessageType::Monitors,
+                data: MessageData::MonitorsMessage(monitors),
+            });
+        }
+    }
+}
+
+/// 前端获取显示器列表，用于选择显示鼠标移动的显示器
+#[tauri::command]
+pub fn get_monitor_list() -> Vec<Monitor> {
+    get_monitors()
+}
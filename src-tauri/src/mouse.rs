@@ -0,0 +1,211 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use once_cell::sync::Lazy;
+use tokio::sync::{broadcast::Sender, Notify};
+
+use crate::{
+    inputs::{InputInfo, InputMessage},
+    message::{Message, MessageData, MessageType},
+    stats, CONFIG,
+};
+
+/// 鼠标移动消息的广播发送器，与按键消息分开，避免高回报率的鼠标挤掉按键消息
+#[derive(Debug, Clone)]
+pub struct MotionSender(pub Sender<Message>);
+
+/// 等待发送的鼠标移动消息，坐标和相对移动各只保留最新的一条
+#[derive(Default)]
+struct Pending {
+    coord: Option<InputMessage>,
+    motion: Option<InputMessage>,
+}
+
+static PENDING: Lazy<Mutex<Pending>> = Lazy::new(|| Mutex::new(Pending::default()));
+
+/// 有新的鼠标移动消息时通知发送task
+static NOTIFY: Lazy<Notify> = Lazy::new(Notify::new);
+
+/// 鼠标停止移动超过该时间后，以下一次移动的位置重新开始计算相对移动
+const MOTION_IDLE_RESET: Duration = Duration::from_millis(100);
+
+/// 鼠标相对移动
+#[derive(Debug, Clone, PartialEq)]
+pub struct Motion {
+    pub dx: f64,
+    pub dy: f64,
+    pub distance: f64,
+    /// 锚点之后移动路径的平均速度(px/s)
+    pub speed: f64,
+}
+
+/// 鼠标相对移动检测，移出锚点的半径范围时产生相对移动
+#[derive(Debug, Default)]
+pub struct MotionTracker {
+    /// 锚点的位置和时间
+    anchor: Option<(f64, f64, SystemTime)>,
+    /// 上一次采样的位置和时间
+    last: Option<(f64, f64, SystemTime)>,
+    /// 锚点之后移动路径的长度
+    path: f64,
+}
+
+impl MotionTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 清除锚点
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 记录鼠标位置，离锚点的距离达到半径时返回相对移动，并以当前位置作为新的锚点
+    pub fn push(&mut self, x: f64, y: f64, time: SystemTime, radius: f64) -> Option<Motion> {
+        // 停止移动一段时间后重新开始，速度不计入停顿的时间
+        let idle = self
+            .last
+            .and_then(|(_, _, last)| time.duration_since(last).ok())
+            .map_or(false, |gap| gap > MOTION_IDLE_RESET);
+        let (anchor_x, anchor_y, anchor_time) = match self.anchor {
+            Some(anchor) if !idle => anchor,
+            _ => {
+                self.anchor = Some((x, y, time));
+                self.last = Some((x, y, time));
+                self.path = 0.0;
+                return None;
+            }
+        };
+        if let Some((last_x, last_y, _)) = self.last {
+            self.path += (x - last_x).hypot(y - last_y);
+        }
+        self.last = Some((x, y, time));
+        let (dx, dy) = (x - anchor_x, y - anchor_y);
+        let distance = dx.hypot(dy);
+        if distance == 0.0 || distance < radius {
+            return None;
+        }
+        let secs = time
+            .duration_since(anchor_time)
+            .map(|elapsed| elapsed.as_secs_f64())
+            .unwrap_or(0.0)
+            .max(0.001);
+        let speed = self.path / secs;
+        self.anchor = Some((x, y, time));
+        self.path = 0.0;
+        Some(Motion {
+            dx,
+            dy,
+            distance,
+            speed,
+        })
+    }
+}
+
+/// 放入鼠标移动消息，上一条还未发送时会被替换
+pub fn push_move(message: InputMessage) {
+    let mut pending = PENDING.lock().unwrap();
+    let slot = match message.info {
+        InputInfo::Motion { .. } => &mut pending.motion,
+        _ => &mut pending.coord,
+    };
+    if let Some(_) = slot.replace(message) {
+        stats::record_coalesced_move();
+    }
+    drop(pending);
+    NOTIFY.notify_one();
+}
+
+/// 发送鼠标移动消息，按设置的频率合并，频率为0时不合并
+pub async fn run(sender: MotionSender) {
+    loop {
+        NOTIFY.notified().await;
+        let messages = {
+            let mut pending = PENDING.lock().unwrap();
+            [pending.coord.take(), pending.motion.take()]
+        };
+        for message in messages.into_iter().flatten() {
+            let _ = sender.0.send(Message {
+                r#type: MessageType::Input,
+                data: MessageData::InputMessage(message),
+            });
+        }
+        let rate = unsafe { CONFIG.lock().unwrap().mouse_move_rate_hz };
+        if rate > 0 {
+            // 发送后等待一个周期，期间的移动只保留最新的一条
+            tokio::time::sleep(Duration::from_secs_f64(1.0 / rate as f64)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000) + Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn first_sample_sets_anchor() {
+        let mut tracker = MotionTracker::new();
+        assert_eq!(tracker.push(0.0, 0.0, at(0), 50.0), None);
+        assert_eq!(tracker.push(30.0, 0.0, at(10), 50.0), None);
+    }
+
+    #[test]
+    fn motion_when_leaving_radius() {
+        let mut tracker = MotionTracker::new();
+        tracker.push(0.0, 0.0, at(0), 50.0);
+        tracker.push(30.0, 0.0, at(10), 50.0);
+        let motion = tracker.push(60.0, 0.0, at(20), 50.0).unwrap();
+        assert_eq!((motion.dx, motion.dy, motion.distance), (60.0, 0.0, 60.0));
+        assert_eq!(motion.speed, 3000.0);
+        // 新的锚点为产生移动时的位置
+        assert_eq!(tracker.push(90.0, 0.0, at(30), 50.0), None);
+    }
+
+    #[test]
+    fn idle_time_is_not_counted() {
+        let mut tracker = MotionTracker::new();
+        tracker.push(0.0, 0.0, at(0), 50.0);
+        tracker.push(10.0, 0.0, at(10), 50.0);
+        // 停顿5秒后快速移动，速度只按停顿后的移动计算
+        assert_eq!(tracker.push(10.0, 0.0, at(5_010), 50.0), None);
+        let motion = tracker.push(70.0, 0.0, at(5_030), 50.0).unwrap();
+        assert_eq!(motion.dx, 60.0);
+        assert_eq!(motion.speed, 3000.0);
+    }
+
+    #[test]
+    fn short_pause_keeps_anchor() {
+        let mut tracker = MotionTracker::new();
+        tracker.push(0.0, 0.0, at(0), 50.0);
+        tracker.push(30.0, 0.0, at(10), 50.0);
+        let motion = tracker.push(60.0, 0.0, at(110), 50.0).unwrap();
+        assert_eq!(motion.dx, 60.0);
+        assert!((motion.speed - 545.45).abs() < 0.01);
+    }
+
+    #[test]
+    fn speed_follows_the_path() {
+        let mut tracker = MotionTracker::new();
+        tracker.push(0.0, 0.0, at(0), 50.0);
+        tracker.push(30.0, 0.0, at(10), 50.0);
+        assert_eq!(tracker.push(30.0, 30.0, at(20), 50.0), None);
+        let motion = tracker.push(0.0, 60.0, at(30), 50.0).unwrap();
+        assert_eq!((motion.dx, motion.dy), (0.0, 60.0));
+        // 路径长度30 + 30 + 42.43
+        assert!((motion.speed - 3414.2).abs() < 0.1);
+    }
+
+    #[test]
+    fn reset_clears_anchor() {
+        let mut tracker = MotionTracker::new();
+        tracker.push(0.0, 0.0, at(0), 50.0);
+        tracker.reset();
+        assert_eq!(tracker.push(60.0, 0.0, at(10), 50.0), None);
+    }
+}
@@ -0,0 +1,708 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, DirEntry},
+    io::{Error, Read},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tauri::State;
+
+use crate::{
+    constants,
+    file::{get_dir_entries, write_file_atomic, FileError},
+    inputs::{InputInfo, InputSource},
+    message::{KeySender, MessageData},
+    thumbnail::get_thumbnail,
+};
+
+/// 预设名的最大长度
+const PRESET_NAME_MAX_LEN: usize = 64;
+/// 等待按键捕获的默认超时时间(ms)
+const CAPTURE_KEY_DEFAULT_TIMEOUT: u64 = 10000;
+
+/// 内置预设目录，在tauri启动后设置
+static RESOURCE_PRESETS_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+#[derive(Debug, Serialize)]
+pub enum PresetError {
+    ReadFilesError,
+    FileTypeError,
+    NotDirError,
+    NotPresetError,
+    NotFoundError,
+    AlreadyExistsError,
+    InvalidNameError,
+    InvalidManifestError,
+    InvalidImageError,
+    WriteFilesError,
+    CaptureTimeoutError,
+    ParentNotFoundError,
+    InheritanceCycleError,
+}
+
+/// 预设配置文件内容
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PresetManifest {
+    /// 显示名，为空时显示预设文件夹名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    /// 作者
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// 描述
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// 继承的父预设名，父预设的按键区域和样式会被同名项覆盖
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends: Option<String>,
+    /// 预设图片宽度(px)，继承时可省略
+    #[serde(default)]
+    pub width: u32,
+    /// 预设图片高度(px)，继承时可省略
+    #[serde(default)]
+    pub height: u32,
+    /// 按键区域
+    #[serde(default)]
+    pub keys: Vec<KeyRegion>,
+    /// 按键样式，键为样式名，由客户端解释
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub styles: BTreeMap<String, Value>,
+    /// 预设推荐的设置，切换到该预设时应用
+    #[serde(default, skip_serializing_if = "PresetDefaults::is_empty")]
+    pub defaults: PresetDefaults,
+}
+
+/// 预设推荐的设置，为空的项使用全局默认值
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PresetDefaults {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_down_transition_duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_up_transition_duration: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mouse_move_radius_px: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mouse_move_transition_duration: Option<u64>,
+}
+
+/// 预设列表中展示的预设信息
+#[derive(Debug, Clone, Serialize)]
+pub struct PresetInfo {
+    /// 预设名(文件夹名)
+    pub name: String,
+    /// 显示名
+    pub display_name: String,
+    pub author: Option<String>,
+    pub description: Option<String>,
+    /// 绑定的按键数量
+    pub key_count: usize,
+    /// 缩略图data uri
+    pub thumbnail: Option<String>,
+}
+
+/// 单个按键在预设图片上的区域
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyRegion {
+    /// 按键名，与keys.rs中的名字对应
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// 使用的样式名
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub style: Option<String>,
+}
+
+impl PresetDefaults {
+    pub fn is_empty(&self) -> bool {
+        self.eq(&PresetDefaults::default())
+    }
+}
+
+impl PresetManifest {
+    /// 校验按键区域是否合法
+    pub fn validate(&self) -> Result<(), PresetError> {
+        if self.width == 0 || self.height == 0 {
+            return Err(PresetError::InvalidManifestError);
+        }
+        for key in self.keys.iter() {
+            if key.name.trim().is_empty() || key.width == 0 || key.height == 0 {
+                return Err(PresetError::InvalidManifestError);
+            }
+            // 区域不能超出图片范围
+            if key.x.saturating_add(key.width) > self.width
+                || key.y.saturating_add(key.height) > self.height
+            {
+                return Err(PresetError::InvalidManifestError);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 读取预设列表
+#[tauri::command]
+pub fn get_presets() -> Vec<String> {
+    // 初始化预设数组
+    let mut preset_list: Vec<String> = Vec::new();
+    // 依次读取用户目录和资源目录，同名预设以用户目录为准
+    for path_buf in get_preset_dirs() {
+        // 获取文件夹文件
+        let read_dir = get_dir_entries(Some(path_buf));
+        if let Err(_) = read_dir {
+            continue;
+        }
+        // 遍历文件入口
+        for entry_res in read_dir.unwrap() {
+            let preset_res = get_single_preset(entry_res);
+            if let Err(_) = preset_res {
+                continue;
+            }
+            let preset = preset_res.unwrap();
+            if !preset_list.contains(&preset) {
+                preset_list.push(preset);
+            }
+        }
+    }
+    preset_list
+}
+
+/// 读取预设列表及每个预设的信息和缩略图
+#[tauri::command]
+pub async fn get_preset_infos() -> Vec<PresetInfo> {
+    // 读取文件和生成缩略图较慢，不阻塞异步运行时
+    tokio::task::spawn_blocking(read_preset_infos)
+        .await
+        .unwrap_or_default()
+}
+
+/// 依次读取所有预设的信息，无法读取的预设跳过
+fn read_preset_infos() -> Vec<PresetInfo> {
+    let mut infos = Vec::new();
+    for name in get_presets() {
+        let manifest = match resolve_manifest(&name) {
+            Ok(manifest) => manifest,
+            Err(_) => continue,
+        };
+        let mut key_names: Vec<&String> = manifest.keys.iter().map(|key| &key.name).collect();
+        key_names.sort();
+        key_names.dedup();
+        let thumbnail = resolve_image(&name)
+            .ok()
+            .and_then(|image| get_thumbnail(&image));
+        infos.push(PresetInfo {
+            display_name: manifest.display_name.clone().unwrap_or(name.clone()),
+            name,
+            author: manifest.author.clone(),
+            description: manifest.description.clone(),
+            key_count: key_names.len(),
+            thumbnail,
+        });
+    }
+    infos
+}
+
+/// 读取单个预设的配置(不合并父预设)
+#[tauri::command]
+pub fn get_preset(name: String) -> Result<PresetManifest, PresetError> {
+    check_preset_name(&name)?;
+    let dir = find_preset_dir(&name).ok_or(PresetError::NotFoundError)?;
+    read_manifest(&dir)
+}
+
+/// 读取单个预设合并父预设后的配置
+#[tauri::command]
+pub fn get_resolved_preset(name: String) -> Result<PresetManifest, PresetError> {
+    check_preset_name(&name)?;
+    resolve_manifest(&name)
+}
+
+/// 保存单个预设的配置，内置预设会保存为同名的用户预设
+#[tauri::command]
+pub fn save_preset(name: String, manifest: PresetManifest) -> Result<(), PresetError> {
+    check_preset_name(&name)?;
+    let source_dir = find_preset_dir(&name).ok_or(PresetError::NotFoundError)?;
+    // 校验合并父预设后的配置，同时检查继承链
+    merge_chain(get_chain(&name, manifest.clone())?).validate()?;
+    let target_dir = user_preset_dir(&name);
+    if source_dir != target_dir {
+        copy_preset_files(&source_dir, &target_dir)?;
+    }
+    write_manifest(&target_dir, &manifest)
+}
+
+/// 通过一张png图片新建空白预设
+#[tauri::command]
+pub fn create_preset_from_image(
+    name: String,
+    image_path: String,
+) -> Result<PresetManifest, PresetError> {
+    check_preset_name(&name)?;
+    if find_preset_dir(&name).is_some() {
+        return Err(PresetError::AlreadyExistsError);
+    }
+    let image = fs::read(&image_path).map_err(|_| PresetError::ReadFilesError)?;
+    let (width, height) = get_png_size(&image).ok_or(PresetError::InvalidImageError)?;
+    let manifest = PresetManifest {
+        width,
+        height,
+        ..Default::default()
+    };
+    let target_dir = user_preset_dir(&name);
+    fs::create_dir_all(&target_dir).map_err(|_| PresetError::WriteFilesError)?;
+    let res = write_file_atomic(&target_dir.join(constants::PRESET_IMAGE_FILE_NAME), &image)
+        .map_err(|_| PresetError::WriteFilesError)
+        .and_then(|_| write_manifest(&target_dir, &manifest));
+    // 写入失败则不留下不完整的预设
+    if let Err(error) = res {
+        let _ = fs::remove_dir_all(&target_dir);
+        return Err(error);
+    }
+    Ok(manifest)
+}
+
+/// 复制预设为新的用户预设
+#[tauri::command]
+pub fn duplicate_preset(name: String, new_name: String) -> Result<(), PresetError> {
+    check_preset_name(&name)?;
+    check_preset_name(&new_name)?;
+    let source_dir = find_preset_dir(&name).ok_or(PresetError::NotFoundError)?;
+    if find_preset_dir(&new_name).is_some() {
+        return Err(PresetError::AlreadyExistsError);
+    }
+    let target_dir = user_preset_dir(&new_name);
+    if let Err(error) = copy_preset_files(&source_dir, &target_dir) {
+        let _ = fs::remove_dir_all(&target_dir);
+        return Err(error);
+    }
+    Ok(())
+}
+
+/// 删除用户预设，内置预设不能删除
+#[tauri::command]
+pub fn delete_preset(name: String) -> Result<(), PresetError> {
+    check_preset_name(&name)?;
+    let dir = user_preset_dir(&name);
+    if !dir.is_dir() {
+        return Err(PresetError::NotFoundError);
+    }
+    fs::remove_dir_all(dir).map_err(|_| PresetError::WriteFilesError)
+}
+
+/// 等待下一个按下的键盘按键或鼠标按键，返回按键名，用于编辑器中给区域绑定按键
+#[tauri::command]
+pub async fn capture_key(
+    state: State<'_, KeySender>,
+    timeout_ms: Option<u64>,
+) -> Result<String, PresetError> {
+    let mut rx = state.subscribe();
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(CAPTURE_KEY_DEFAULT_TIMEOUT));
+    let capture = async move {
+        loop {
+            let msg = rx.recv().await?;
+            if let MessageData::InputMessage(input) = msg.data {
+                if let InputSource::MouseMove | InputSource::MouseWheel = input.source {
+                    continue;
+                }
+                if let InputInfo::Pressing {
+                    name,
+                    pressing: true,
+                    ..
+                } = input.info
+                {
+                    return Some(name);
+                }
+            }
+        }
+    };
+    match tokio::time::timeout(timeout, capture).await {
+        Ok(Some(name)) => Ok(name),
+        _ => Err(PresetError::CaptureTimeoutError),
+    }
+}
+
+/// 设置内置预设目录
+pub fn set_resource_dir(path: Option<PathBuf>) {
+    if let Some(path_buf) = path {
+        let _ = RESOURCE_PRESETS_DIR.set(path_buf);
+    }
+}
+
+/// 获取预设所在的目录列表，用户目录优先
+pub fn get_preset_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(constants::USER_PRESETS_DIR)];
+    if let Some(path_buf) = RESOURCE_PRESETS_DIR.get() {
+        dirs.push(path_buf.clone());
+    }
+    dirs
+}
+
+/// 通过预设名查找预设目录
+pub fn find_preset_dir(name: &str) -> Option<PathBuf> {
+    get_preset_dirs()
+        .into_iter()
+        .map(|dir| dir.join(name))
+        .find(|dir| dir.join(constants::PRESET_CONFIG_FILE_NAME).is_file())
+}
+
+/// 读取预设目录中的配置，配置文件为空时根据图片生成空白配置
+/// 图片无法读取(如内置的空白默认预设)时尺寸为0
+pub fn read_manifest(dir: &Path) -> Result<PresetManifest, PresetError> {
+    let content = fs::read_to_string(dir.join(constants::PRESET_CONFIG_FILE_NAME))
+        .map_err(|_| PresetError::ReadFilesError)?;
+    if content.trim().is_empty() {
+        let (width, height) =
+            read_png_size(&dir.join(constants::PRESET_IMAGE_FILE_NAME)).unwrap_or((0, 0));
+        return Ok(PresetManifest {
+            width,
+            height,
+            ..Default::default()
+        });
+    }
+    serde_json::from_str(&content).map_err(|_| PresetError::InvalidManifestError)
+}
+
+/// 读取预设并合并继承链上的所有父预设
+pub fn resolve_manifest(name: &str) -> Result<PresetManifest, PresetError> {
+    let dir = find_preset_dir(name).ok_or(PresetError::NotFoundError)?;
+    Ok(merge_chain(get_chain(name, read_manifest(&dir)?)?))
+}
+
+/// 获取预设使用的图片，预设本身没有图片时使用最近的父预设的图片
+pub fn resolve_image(name: &str) -> Result<PathBuf, PresetError> {
+    let dir = find_preset_dir(name).ok_or(PresetError::NotFoundError)?;
+    for (preset_name, _) in get_chain(name, read_manifest(&dir)?)? {
+        let image = find_preset_dir(&preset_name)
+            .map(|dir| dir.join(constants::PRESET_IMAGE_FILE_NAME))
+            .filter(|image| image.is_file());
+        if let Some(image) = image {
+            return Ok(image);
+        }
+    }
+    Err(PresetError::InvalidImageError)
+}
+
+/// 获取继承链，从预设本身开始依次到最顶层的父预设
+fn get_chain(
+    name: &str,
+    manifest: PresetManifest,
+) -> Result<Vec<(String, PresetManifest)>, PresetError> {
+    let mut parent = manifest.extends.clone();
+    let mut chain = vec![(name.to_string(), manifest)];
+    while let Some(parent_name) = parent {
+        if chain
+            .iter()
+            .any(|(preset_name, _)| preset_name.eq(&parent_name))
+        {
+            return Err(PresetError::InheritanceCycleError);
+        }
+        // 父预设名不能包含路径
+        check_preset_name(&parent_name).map_err(|_| PresetError::InvalidManifestError)?;
+        let dir = find_preset_dir(&parent_name).ok_or(PresetError::ParentNotFoundError)?;
+        let parent_manifest = read_manifest(&dir)?;
+        parent = parent_manifest.extends.clone();
+        chain.push((parent_name, parent_manifest));
+    }
+    Ok(chain)
+}
+
+/// 从最顶层的父预设开始依次合并继承链
+fn merge_chain(chain: Vec<(String, PresetManifest)>) -> PresetManifest {
+    let mut merged = chain
+        .into_iter()
+        .rev()
+        .map(|(_, manifest)| manifest)
+        .reduce(merge_manifest)
+        .unwrap_or_default();
+    merged.extends = None;
+    merged
+}
+
+/// 用子预设覆盖父预设，同名按键区域整体替换，同名样式逐字段覆盖
+fn merge_manifest(parent: PresetManifest, child: PresetManifest) -> PresetManifest {
+    let parent_names: Vec<String> = parent.keys.iter().map(|key| key.name.clone()).collect();
+    let mut keys: Vec<KeyRegion> = Vec::new();
+    // 保持父预设中按键的顺序，被覆盖的按键替换为子预设中的区域
+    for region in parent.keys.into_iter() {
+        if !child.keys.iter().any(|key| key.name.eq(&region.name)) {
+            keys.push(region);
+        } else if !keys.iter().any(|key| key.name.eq(&region.name)) {
+            keys.extend(
+                child
+                    .keys
+                    .iter()
+                    .filter(|key| key.name.eq(&region.name))
+                    .cloned(),
+            );
+        }
+    }
+    // 子预设新增的按键
+    for region in child.keys.into_iter() {
+        if !parent_names.contains(&region.name) {
+            keys.push(region);
+        }
+    }
+    let mut styles = parent.styles;
+    for (style_name, style) in child.styles.into_iter() {
+        match (styles.get_mut(&style_name), style) {
+            (Some(Value::Object(parent_style)), Value::Object(child_style)) => {
+                parent_style.extend(child_style);
+            }
+            (_, style) => {
+                styles.insert(style_name, style);
+            }
+        }
+    }
+    PresetManifest {
+        // 显示名不继承，避免变体与父预设同名
+        display_name: child.display_name,
+        author: child.author.or(parent.author),
+        description: child.description.or(parent.description),
+        extends: parent.extends,
+        width: if child.width > 0 {
+            child.width
+        } else {
+            parent.width
+        },
+        height: if child.height > 0 {
+            child.height
+        } else {
+            parent.height
+        },
+        keys,
+        styles,
+        defaults: PresetDefaults {
+            key_down_transition_duration: child
+                .defaults
+                .key_down_transition_duration
+                .or(parent.defaults.key_down_transition_duration),
+            key_up_transition_duration: child
+                .defaults
+                .key_up_transition_duration
+                .or(parent.defaults.key_up_transition_duration),
+            mouse_move_radius_px: child
+                .defaults
+                .mouse_move_radius_px
+                .or(parent.defaults.mouse_move_radius_px),
+            mouse_move_transition_duration: child
+                .defaults
+                .mouse_move_transition_duration
+                .or(parent.defaults.mouse_move_transition_duration),
+        },
+    }
+}
+
+/// 写入预设配置
+pub fn write_manifest(dir: &Path, manifest: &PresetManifest) -> Result<(), PresetError> {
+    let content =
+        serde_json::to_string_pretty(manifest).map_err(|_| PresetError::InvalidManifestError)?;
+    write_file_atomic(
+        &dir.join(constants::PRESET_CONFIG_FILE_NAME),
+        content.as_bytes(),
+    )
+    .map_err(|_| PresetError::WriteFilesError)
+}
+
+/// 将预设的图片和配置复制到目标目录，继承父预设图片的预设可以没有图片
+fn copy_preset_files(source_dir: &Path, target_dir: &Path) -> Result<(), PresetError> {
+    fs::create_dir_all(target_dir).map_err(|_| PresetError::WriteFilesError)?;
+    for file_name in [
+        constants::PRESET_IMAGE_FILE_NAME,
+        constants::PRESET_CONFIG_FILE_NAME,
+    ] {
+        if file_name.eq(constants::PRESET_IMAGE_FILE_NAME) && !source_dir.join(file_name).is_file()
+        {
+            continue;
+        }
+        let content =
+            fs::read(source_dir.join(file_name)).map_err(|_| PresetError::ReadFilesError)?;
+        write_file_atomic(&target_dir.join(file_name), &content)
+            .map_err(|_| PresetError::WriteFilesError)?;
+    }
+    Ok(())
+}
+
+/// 用户预设目录
+pub fn user_preset_dir(name: &str) -> PathBuf {
+    PathBuf::from(constants::USER_PRESETS_DIR).join(name)
+}
+
+/// 检查预设名，预设名会作为文件夹名，不能包含路径
+pub fn check_preset_name(name: &str) -> Result<(), PresetError> {
+    let invalid = name.trim().is_empty()
+        || name.len() > PRESET_NAME_MAX_LEN
+        || name.starts_with('.')
+        || name
+            .chars()
+            .any(|c| c.is_control() || ['/', '\\', ':', '*', '?', '"', '<', '>', '|'].contains(&c));
+    if invalid {
+        return Err(PresetError::InvalidNameError);
+    }
+    Ok(())
+}
+
+/// 读取png文件头中的图片尺寸
+fn read_png_size(path: &Path) -> Option<(u32, u32)> {
+    let mut header = [0u8; 24];
+    let mut file = fs::File::open(path).ok()?;
+    file.read_exact(&mut header).ok()?;
+    get_png_size(&header)
+}
+
+/// 从png数据中获取图片尺寸(IHDR块)
+fn get_png_size(data: &[u8]) -> Option<(u32, u32)> {
+    if data.len() < 24 || &data[0..8] != b"\x89PNG\r\n\x1a\n" || &data[12..16] != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes([data[16], data[17], data[18], data[19]]);
+    let height = u32::from_be_bytes([data[20], data[21], data[22], data[23]]);
+    if width == 0 || height == 0 {
+        return None;
+    }
+    Some((width, height))
+}
+
+/// 读取并判断文件夹是否为单个预设文件夹
+fn get_single_preset(entry_res: Result<DirEntry, Error>) -> Result<String, PresetError> {
+    if let Err(_) = entry_res {
+        return Err(PresetError::ReadFilesError);
+    }
+    let entry = entry_res.unwrap();
+    // 读取文件类型
+    let file_type_res = entry.file_type();
+    if let Err(_) = file_type_res {
+        return Err(PresetError::FileTypeError);
+    }
+    // 是否为文件夹
+    if !file_type_res.unwrap().is_dir() {
+        return Err(PresetError::NotDirError);
+    }
+    // 读取文件夹
+    let file_path = entry.path();
+    let read_dir_res = get_dir_entries(Some(file_path));
+    if let Err(_) = read_dir_res {
+        return Err(PresetError::ReadFilesError);
+    }
+    // 判断文件
+    let mut has_image = false;
+    let mut has_config = false;
+    for entry_res in read_dir_res.unwrap() {
+        if let Err(_) = entry_res {
+            continue;
+        }
+        // 判断文件名
+        let file_name = get_file_name(entry_res.unwrap()).unwrap_or("".to_string());
+        if file_name.eq("") {
+            continue;
+        } else if file_name.eq(constants::PRESET_IMAGE_FILE_NAME) {
+            has_image = true;
+        } else if file_name.eq(constants::PRESET_CONFIG_FILE_NAME) {
+            has_config = true;
+        };
+        // 图片和配置文件都存在则
+        if has_image && has_config {
+            return if let Some(str) = entry.file_name().to_str() {
+                Ok(str.to_string())
+            } else {
+                Err(PresetError::ReadFilesError)
+            };
+        }
+    }
+    // 没有图片但继承了父预设时也是预设
+    if has_config {
+        if let Ok(PresetManifest {
+            extends: Some(_), ..
+        }) = read_manifest(&entry.path())
+        {
+            if let Some(str) = entry.file_name().to_str() {
+                return Ok(str.to_string());
+            }
+        }
+    }
+    Err(PresetError::NotPresetError)
+}
+
+/// 获取文件名，如果不是文件或者其他则返回错误
+fn get_file_name(entry: DirEntry) -> Result<String, FileError> {
+    let file_type_res = entry.file_type();
+    if let Err(_) = file_type_res {
+        return Err(FileError::FileTypeError);
+    }
+    if file_type_res.unwrap().is_file() {
+        match entry.file_name().to_str() {
+            Some(str) => Ok(str.to_string()),
+            None => Err(FileError::ReadDirError),
+        }
+    } else {
+        return Err(FileError::NotFileError);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 创建只包含给定文件的临时预设目录
+    fn preset_dir(name: &str, files: &[(&str, &[u8])]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("preset-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file_name, content) in files {
+            fs::write(dir.join(file_name), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn parent_name_with_path_is_invalid() {
+        for parent_name in ["../other", "a/b", ""] {
+            let manifest = PresetManifest {
+                extends: Some(parent_name.to_string()),
+                ..Default::default()
+            };
+            assert!(matches!(
+                get_chain("child", manifest),
+                Err(PresetError::InvalidManifestError)
+            ));
+        }
+    }
+
+    #[test]
+    fn empty_config_and_image() {
+        // 与内置的默认预设相同，两个文件都为空
+        let dir = preset_dir(
+            "empty",
+            &[
+                (constants::PRESET_CONFIG_FILE_NAME, b""),
+                (constants::PRESET_IMAGE_FILE_NAME, b""),
+            ],
+        );
+        let manifest = read_manifest(&dir).unwrap();
+        assert_eq!((manifest.width, manifest.height), (0, 0));
+        assert!(manifest.keys.is_empty());
+        assert!(manifest.extends.is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn empty_config_uses_image_size() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&640u32.to_be_bytes());
+        png.extend_from_slice(&480u32.to_be_bytes());
+        let dir = preset_dir(
+            "image",
+            &[
+                (constants::PRESET_CONFIG_FILE_NAME, b" \n"),
+                (constants::PRESET_IMAGE_FILE_NAME, &png),
+            ],
+        );
+        let manifest = read_manifest(&dir).unwrap();
+        assert_eq!((manifest.width, manifest.height), (640, 480));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,495 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::{
+    select,
+    sync::{
+        broadcast::{error::RecvError, Receiver, Sender},
+        mpsc::{self, UnboundedReceiver, UnboundedSender},
+        oneshot,
+    },
+    task::JoinHandle,
+};
+
+use crate::{
+    constants,
+    inputs::{self, InputInfo, InputMessage},
+    message::{KeySender, Message, MessageData, MessageType},
+    mouse::MotionSender,
+};
+
+#[derive(Debug, Serialize)]
+pub enum RecordError {
+    AlreadyRecordingError,
+    NotRecordingError,
+    NotReplayingError,
+    CreateFileError,
+    ReadFileError,
+    InvalidRecordingError,
+    InvalidSpeedError,
+}
+
+/// 录制文件(JSON Lines)中的一行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedInput {
+    /// 相对第一条输入的时间(ms)，按输入时间计算
+    pub offset_ms: u64,
+    pub message: InputMessage,
+}
+
+/// 回放控制命令
+#[derive(Debug, Clone)]
+pub enum ReplayCommand {
+    /// 步进模式下发送下一条输入，普通模式下跳过当前等待
+    Step,
+    Stop,
+}
+
+/// 正在进行的录制
+struct Recording {
+    path: PathBuf,
+    stop: oneshot::Sender<()>,
+    /// 录制task，结束时返回录制的输入数
+    task: JoinHandle<u64>,
+}
+
+static RECORDING: Lazy<Mutex<Option<Recording>>> = Lazy::new(|| Mutex::new(None));
+
+/// 正在进行的回放的控制发送器
+static REPLAY: Lazy<Mutex<Option<UnboundedSender<ReplayCommand>>>> = Lazy::new(|| Mutex::new(None));
+
+/// 开始录制广播中的输入消息(包括鼠标移动)，未指定路径时保存到录制目录，返回录制文件路径
+pub fn start_recording(
+    sender: &Sender<Message>,
+    key_sender: &KeySender,
+    motion_sender: &MotionSender,
+    path: Option<PathBuf>,
+) -> Result<PathBuf, RecordError> {
+    let mut recording = RECORDING.lock().unwrap();
+    if recording.is_some() {
+        return Err(RecordError::AlreadyRecordingError);
+    }
+    let path = path.unwrap_or_else(|| {
+        Path::new(constants::RECORDINGS_DIR)
+            .join(format!("{}.jsonl", Local::now().format("%Y%m%d-%H%M%S")))
+    });
+    if let Some(parent) = path.parent() {
+        if let Err(_) = fs::create_dir_all(parent) {
+            return Err(RecordError::CreateFileError);
+        }
+    }
+    let file = File::create(&path).map_err(|_| RecordError::CreateFileError)?;
+    let (stop, stop_receiver) = oneshot::channel();
+    let task = tokio::spawn(record(
+        BufWriter::new(file),
+        sender.subscribe(),
+        key_sender.subscribe(),
+        motion_sender.0.subscribe(),
+        stop_receiver,
+    ));
+    *recording = Some(Recording {
+        path: path.clone(),
+        stop,
+        task,
+    });
+    Ok(path)
+}
+
+/// 停止录制，返回录制文件路径和录制的输入数
+pub async fn stop_recording() -> Result<(PathBuf, u64), RecordError> {
+    let recording = RECORDING.lock().unwrap().take();
+    let recording = recording.ok_or(RecordError::NotRecordingError)?;
+    let _ = recording.stop.send(());
+    let count = recording.task.await.unwrap_or(0);
+    Ok((recording.path, count))
+}
+
+/// 录制task，将输入消息逐行写入文件
+async fn record(
+    mut writer: BufWriter<File>,
+    mut receiver: Receiver<Message>,
+    mut key_receiver: mpsc::Receiver<Message>,
+    mut motion_receiver: Receiver<Message>,
+    mut stop: oneshot::Receiver<()>,
+) -> u64 {
+    // 录制期间发送按键重复消息
+    let _repeat_subscription = inputs::subscribe_repeat();
+    // 第一条输入的时间，之后的输入按输入时间计算相对时间
+    let mut first: Option<(SystemTime, Instant)> = None;
+    let mut count = 0;
+    loop {
+        let message = select! {
+            _ = &mut stop => break,
+            message = key_receiver.recv() => message.ok_or(RecvError::Closed),
+            message = receiver.recv() => message,
+            message = motion_receiver.recv() => message,
+        };
+        let message = match message {
+            Ok(Message {
+                r#type: MessageType::Input,
+                data: MessageData::InputMessage(message),
+            }) => message,
+            Ok(_) => continue,
+            Err(RecvError::Lagged(skipped)) => {
+                eprintln!("recording lagged, {} inputs skipped", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        let (first_time, started) = *first.get_or_insert((message.time, Instant::now()));
+        // 输入时间早于第一条输入时(如系统时间被调整)使用接收的时间
+        let offset = message
+            .time
+            .duration_since(first_time)
+            .unwrap_or_else(|_| started.elapsed());
+        let line = RecordedInput {
+            offset_ms: offset.as_millis() as u64,
+            message,
+        };
+        if let Err(error) = writeln!(writer, "{}", serde_json::to_string(&line).unwrap()) {
+            eprintln!("recording write error: {:?}", error);
+            break;
+        }
+        count += 1;
+    }
+    let _ = writer.flush();
+    count
+}
+
+/// 读取录制文件
+pub fn read_recording(path: &Path) -> Result<Vec<RecordedInput>, RecordError> {
+    let file = File::open(path).map_err(|_| RecordError::ReadFileError)?;
+    let mut inputs = Vec::new();
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|_| RecordError::ReadFileError)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let input: RecordedInput =
+            serde_json::from_str(&line).map_err(|_| RecordError::InvalidRecordingError)?;
+        inputs.push(input);
+    }
+    Ok(inputs)
+}
+
+/// 开始回放录制文件，speed为回放倍速，step为true时每次收到Step命令才发送下一条输入
+/// 已有回放时先停止，返回回放task
+pub fn start_replay(
+    sender: &Sender<Message>,
+    key_sender: &KeySender,
+    motion_sender: &MotionSender,
+    path: &Path,
+    speed: f64,
+    step: bool,
+) -> Result<JoinHandle<()>, RecordError> {
+    if !step && !(speed.is_finite() && speed > 0.0) {
+        return Err(RecordError::InvalidSpeedError);
+    }
+    let inputs = read_recording(path)?;
+    let (commands, receiver) = mpsc::unbounded_channel();
+    if let Some(previous) = REPLAY.lock().unwrap().replace(commands.clone()) {
+        let _ = previous.send(ReplayCommand::Stop);
+    }
+    let sender = sender.clone();
+    let key_sender = key_sender.clone();
+    let motion_sender = motion_sender.clone();
+    Ok(tokio::spawn(async move {
+        replay(
+            sender,
+            key_sender,
+            motion_sender,
+            inputs,
+            speed,
+            step,
+            receiver,
+        )
+        .await;
+        // 回放结束，若没有新的回放则清除控制发送器
+        let mut replay = REPLAY.lock().unwrap();
+        if let Some(current) = replay.as_ref() {
+            if current.same_channel(&commands) {
+                *replay = None;
+            }
+        }
+    }))
+}
+
+/// 向正在进行的回放发送命令
+pub fn control_replay(command: ReplayCommand) -> Result<(), RecordError> {
+    match REPLAY.lock().unwrap().as_ref() {
+        Some(commands) => commands
+            .send(command)
+            .map_err(|_| RecordError::NotReplayingError),
+        None => Err(RecordError::NotReplayingError),
+    }
+}
+
+/// 回放task，将录制的输入以当前时间重新广播
+async fn replay(
+    sender: Sender<Message>,
+    key_sender: KeySender,
+    motion_sender: MotionSender,
+    inputs: Vec<RecordedInput>,
+    speed: f64,
+    step: bool,
+    mut commands: UnboundedReceiver<ReplayCommand>,
+) {
+    // 回放中按住的按键，回放停止时释放
+    let mut pressing: HashMap<String, InputMessage> = HashMap::new();
+    let mut last_offset = 0;
+    for input in inputs {
+        if step {
+            match commands.recv().await {
+                Some(ReplayCommand::Step) => {}
+                _ => break,
+            }
+        } else {
+            let delay = input.offset_ms.saturating_sub(last_offset) as f64 / speed;
+            let command = select! {
+                _ = tokio::time::sleep(Duration::from_secs_f64(delay / 1000.0)) => None,
+                command = commands.recv() => Some(command),
+            };
+            match command {
+                None | Some(Some(ReplayCommand::Step)) => {}
+                Some(_) => break,
+            }
+        }
+        last_offset = input.offset_ms;
+        let mut message = input.message;
+        message.time = SystemTime::now();
+        if let InputInfo::Pressing {
+            name,
+            pressing: down,
+            ..
+        } = &message.info
+        {
+            if *down {
+                pressing.insert(name.clone(), message.clone());
+            } else {
+                pressing.remove(name);
+            }
+        }
+        inputs::send_input(&sender, &key_sender, &motion_sender, message);
+    }
+    for (_, mut message) in pressing {
+        if let InputInfo::Pressing {
+            pressing, held_ms, ..
+        } = &mut message.info
+        {
+            *pressing = false;
+            *held_ms = None;
+        }
+        message.time = SystemTime::now();
+        inputs::send_input(&sender, &key_sender, &motion_sender, message);
+    }
+}
+
+/// 开始录制，返回录制文件路径
+#[tauri::command]
+pub async fn start_input_recording(
+    state: State<'_, Sender<Message>>,
+    key_state: State<'_, KeySender>,
+    motion_state: State<'_, MotionSender>,
+    path: Option<String>,
+) -> Result<String, RecordError> {
+    start_recording(
+        state.inner(),
+        key_state.inner(),
+        motion_state.inner(),
+        path.map(PathBuf::from),
+    )
+    .map(|path| path.to_string_lossy().to_string())
+}
+
+/// 停止录制，返回录制的输入数
+#[tauri::command]
+pub async fn stop_input_recording() -> Result<u64, RecordError> {
+    stop_recording().await.map(|(_, count)| count)
+}
+
+/// 回放录制文件，speed默认为1，step为true时使用步进模式
+#[tauri::command]
+pub async fn start_input_replay(
+    state: State<'_, Sender<Message>>,
+    key_state: State<'_, KeySender>,
+    motion_state: State<'_, MotionSender>,
+    path: String,
+    speed: Option<f64>,
+    step: Option<bool>,
+) -> Result<(), RecordError> {
+    start_replay(
+        state.inner(),
+        key_state.inner(),
+        motion_state.inner(),
+        Path::new(&path),
+        speed.unwrap_or(1.0),
+        step.unwrap_or(false),
+    )
+    .map(|_| ())
+}
+
+/// 步进回放
+#[tauri::command]
+pub fn step_input_replay() -> Result<(), RecordError> {
+    control_replay(ReplayCommand::Step)
+}
+
+/// 停止回放
+#[tauri::command]
+pub fn stop_input_replay() -> Result<(), RecordError> {
+    control_replay(ReplayCommand::Stop)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::broadcast;
+
+    use super::*;
+    use crate::inputs::InputSource;
+
+    fn pressing(name: &str, down: bool) -> InputMessage {
+        InputMessage {
+            source: InputSource::Keyboard,
+            info: InputInfo::Pressing {
+                name: name.to_string(),
+                pressing: down,
+                held_ms: None,
+                physical: Some(name.to_string()),
+                label: None,
+            },
+            time: SystemTime::now(),
+            synthetic: false,
+        }
+    }
+
+    fn input_message(message: InputMessage) -> Message {
+        Message {
+            r#type: MessageType::Input,
+            data: MessageData::InputMessage(message),
+        }
+    }
+
+    /// 取出按键消息中的按键名和是否按下
+    fn describe(message: &InputMessage) -> Option<(String, bool)> {
+        match &message.info {
+            InputInfo::Pressing { name, pressing, .. } => Some((name.clone(), *pressing)),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn recording_round_trip() {
+        let path = std::env::temp_dir().join(format!("record-test-{}.jsonl", std::process::id()));
+        let (sender, _) = broadcast::channel(16);
+        let (motion_sender, _) = broadcast::channel(16);
+        let key_sender = KeySender::new();
+        let (stop, stop_receiver) = oneshot::channel();
+        let task = tokio::spawn(record(
+            BufWriter::new(File::create(&path).unwrap()),
+            sender.subscribe(),
+            key_sender.subscribe(),
+            motion_sender.subscribe(),
+            stop_receiver,
+        ));
+        // 相对时间由输入时间决定，与接收的时间无关
+        let press = pressing("a", true);
+        let release = InputMessage {
+            time: press.time + Duration::from_millis(250),
+            ..pressing("a", false)
+        };
+        key_sender.send(input_message(press));
+        key_sender.send(input_message(release));
+        // 其他类型的消息不录制
+        let _ = sender.send(Message {
+            r#type: MessageType::Test,
+            data: MessageData::TestMessage("test".to_string()),
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let _ = stop.send(());
+        assert_eq!(task.await.unwrap(), 2);
+
+        let inputs = read_recording(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let recorded: Vec<(String, bool)> = inputs
+            .iter()
+            .filter_map(|input| describe(&input.message))
+            .collect();
+        assert_eq!(
+            recorded,
+            vec![("a".to_string(), true), ("a".to_string(), false)]
+        );
+        assert_eq!(inputs[0].offset_ms, 0);
+        assert_eq!(inputs[1].offset_ms, 250);
+    }
+
+    #[test]
+    fn invalid_recording_lines_are_rejected() {
+        let path =
+            std::env::temp_dir().join(format!("record-test-invalid-{}.jsonl", std::process::id()));
+        fs::write(&path, "{\"offset_ms\": 0}\n").unwrap();
+        let res = read_recording(&path);
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(res, Err(RecordError::InvalidRecordingError)));
+    }
+
+    #[tokio::test]
+    async fn held_keys_are_released_when_replay_ends() {
+        let (sender, _) = broadcast::channel(16);
+        let (motion_sender, _) = broadcast::channel(16);
+        let key_sender = KeySender::new();
+        let mut key_receiver = key_sender.subscribe();
+        let inputs = vec![
+            RecordedInput {
+                offset_ms: 0,
+                message: pressing("a", true),
+            },
+            RecordedInput {
+                offset_ms: 5,
+                message: pressing("b", true),
+            },
+            RecordedInput {
+                offset_ms: 10,
+                message: pressing("a", false),
+            },
+        ];
+        // 保留命令发送器，否则回放会认为已被停止
+        let (_commands, receiver) = mpsc::unbounded_channel();
+        replay(
+            sender,
+            key_sender,
+            MotionSender(motion_sender),
+            inputs,
+            10.0,
+            false,
+            receiver,
+        )
+        .await;
+
+        let mut replayed = Vec::new();
+        while let Ok(message) = key_receiver.try_recv() {
+            if let MessageData::InputMessage(message) = message.data {
+                replayed.push(describe(&message).unwrap());
+            }
+        }
+        assert_eq!(
+            replayed,
+            vec![
+                ("a".to_string(), true),
+                ("b".to_string(), true),
+                ("a".to_string(), false),
+                // 回放结束时仍按住的按键
+                ("b".to_string(), false),
+            ]
+        );
+    }
+}
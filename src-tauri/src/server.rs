@@ -2,10 +2,13 @@ use std::future::Future;
 use std::net::{IpAddr, Ipv4Addr};
 
 use port_check;
+use rocket::fs::NamedFile;
 use rocket::futures::TryFutureExt;
+use rocket::http::{ContentType, Status};
 use rocket::response::stream::{Event, EventStream};
+use rocket::serde::json::Json;
 use rocket::{
-    fs::FileServer, futures::channel::mpsc::Receiver, get, routes, Error, Ignite, Rocket, Shutdown,
+    fs::FileServer, futures::channel::mpsc::Receiver, get, post, routes, Error, Ignite, Rocket, Shutdown,
     State,
 };
 use serde::{Deserialize, Serialize};
@@ -18,11 +21,24 @@ use tokio::{
 };
 
 use crate::{
-    message::{Message, MessageData, MessageType},
+    coverage::{self, CoverageReport},
+    focus,
+    inject::{self, InjectError},
+    inputs::{self, InputInfo, InputMessage},
+    message::{KeySender, Message, MessageData, MessageType},
+    mouse::MotionSender,
+    preset::{resolve_image, resolve_manifest, PresetManifest},
+    stats::{self, StatsMessage},
+    usage::{self, UsageReport},
     CONFIG,
 };
 
-pub async fn run(input_sender: Sender<Message>, port: u16) {
+pub async fn run(
+    input_sender: Sender<Message>,
+    key_sender: KeySender,
+    motion_sender: MotionSender,
+    port: u16,
+) {
     let config = rocket::Config {
         port,
         address: IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)),
@@ -34,37 +50,189 @@ pub async fn run(input_sender: Sender<Message>, port: u16) {
         "Hello, world!"
     }
 
-    #[get("/events")]
-    async fn events(sender: &State<Sender<Message>>, mut end: Shutdown) -> EventStream![] {
+    /// 输入事件流，repeat为true时发送按住按键时的重复消息
+    #[get("/events?<repeat>")]
+    async fn events(
+        repeat: Option<bool>,
+        sender: &State<Sender<Message>>,
+        key_sender: &State<KeySender>,
+        motion_sender: &State<MotionSender>,
+        mut end: Shutdown,
+    ) -> EventStream![] {
         let config = unsafe { CONFIG.lock().unwrap().clone() };
+        let repeat = repeat.unwrap_or(false);
+        // 开启repeat的连接存在期间才发送重复消息
+        let repeat_subscription = if repeat {
+            Some(inputs::subscribe_repeat())
+        } else {
+            None
+        };
         let mut rx = sender.subscribe();
+        let mut key_rx = key_sender.subscribe();
+        let mut motion_rx = motion_sender.0.subscribe();
         EventStream! {
+            // 连接断开时stream被drop，同时取消订阅
+            let _repeat_subscription = repeat_subscription;
             // 连接上后首先发送config
             yield Event::json(&Message {
                 r#type: MessageType::Config,
                 data: MessageData::ConfigMessage(config),
             });
+            // 暂停中连接时告知客户端
+            if focus::is_paused() {
+                yield Event::json(&focus::get_message());
+            }
             // 然后循环接收发送msg
             loop {
+                // 优先发送按键消息，鼠标移动消息在单独的通道
                 let msg = select! {
+                    biased;
+                    msg = key_rx.recv() => match msg {
+                        Some(msg) => msg,
+                        None => break,
+                    },
                     msg = rx.recv() => match msg {
                         Ok(msg) => msg,
                         Err(RecvError::Closed) => break,
-                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Lagged(count)) => {
+                            stats::record_dropped(count, false);
+                            continue;
+                        }
+                    },
+                    msg = motion_rx.recv() => match msg {
+                        Ok(msg) => msg,
+                        Err(RecvError::Closed) => break,
+                        Err(RecvError::Lagged(count)) => {
+                            stats::record_dropped(count, true);
+                            continue;
+                        }
                     },
                     _ = &mut end => {
                         rx.resubscribe();
                         break;
                     },
                 };
+                if !repeat && is_repeat(&msg) {
+                    continue;
+                }
                 yield Event::json(&msg);
             }
         }
     }
 
+    /// 当前预设合并父预设后的配置
+    #[get("/preset")]
+    fn preset() -> Option<Json<PresetManifest>> {
+        let name = unsafe { CONFIG.lock().unwrap().preset.clone() }?;
+        resolve_manifest(&name).ok().map(Json)
+    }
+
+    /// 当前预设的图片，没有图片时使用父预设的图片
+    #[get("/preset/image")]
+    async fn preset_image() -> Option<NamedFile> {
+        let name = unsafe { CONFIG.lock().unwrap().preset.clone() }?;
+        NamedFile::open(resolve_image(&name).ok()?).await.ok()
+    }
+
+    /// 当前的统计数据
+    #[get("/stats")]
+    fn current_stats() -> Json<StatsMessage> {
+        Json(stats::snapshot())
+    }
+
+    /// 日期范围内的按键次数，日期格式为YYYY-MM-DD
+    #[get("/usage?<from>&<to>")]
+    fn usage_report(from: Option<String>, to: Option<String>) -> Option<Json<UsageReport>> {
+        usage::query(from, to).ok().map(Json)
+    }
+
+    /// 日期范围内的按键次数热力图
+    #[get("/usage/heatmap?<from>&<to>")]
+    fn usage_heatmap(from: Option<String>, to: Option<String>) -> Option<(ContentType, Vec<u8>)> {
+        usage::render_heatmap(from, to)
+            .ok()
+            .map(|png| (ContentType::PNG, png))
+    }
+
+    /// 当前预设的按键覆盖情况，使用启动或重置后按下过的按键
+    #[get("/debug/coverage")]
+    fn preset_coverage() -> Option<Json<CoverageReport>> {
+        coverage::check_current(None).ok().map(Json)
+    }
+
+    /// 注入模拟输入消息，需要在设置中开启
+    #[post("/inject", format = "json", data = "<messages>")]
+    fn inject_input(
+        messages: Json<Vec<InputMessage>>,
+        sender: &State<Sender<Message>>,
+        key_sender: &State<KeySender>,
+        motion_sender: &State<MotionSender>,
+    ) -> Result<(), (Status, Json<InjectError>)> {
+        inject::inject_messages(
+            sender.inner(),
+            key_sender.inner(),
+            motion_sender.inner(),
+            messages.into_inner(),
+        )
+        .map_err(inject_error)
+    }
+
+    /// 运行输入脚本，每行为一步或用逗号、分号分隔，如"press w 200ms, release w"，需要在设置中开启
+    #[post("/inject/script", data = "<script>")]
+    fn inject_input_script(
+        script: String,
+        sender: &State<Sender<Message>>,
+        key_sender: &State<KeySender>,
+        motion_sender: &State<MotionSender>,
+    ) -> Result<(), (Status, Json<InjectError>)> {
+        inject::inject_script(
+            sender.inner(),
+            key_sender.inner(),
+            motion_sender.inner(),
+            &script,
+        )
+        .map_err(inject_error)
+    }
+
     let _ = rocket::custom(&config)
         .manage(input_sender)
-        .mount("/", routes![index, events])
+        .manage(key_sender)
+        .manage(motion_sender)
+        .mount(
+            "/",
+            routes![
+                index,
+                events,
+                preset,
+                preset_image,
+                current_stats,
+                usage_report,
+                usage_heatmap,
+                inject_input,
+                inject_input_script,
+                preset_coverage
+            ],
+        )
         .launch()
         .await;
 }
+
+/// 注入错误对应的状态码
+fn inject_error(error: InjectError) -> (Status, Json<InjectError>) {
+    let status = match error {
+        InjectError::DisabledError => Status::Forbidden,
+        InjectError::InvalidScriptError => Status::BadRequest,
+    };
+    (status, Json(error))
+}
+
+/// 是否为按住按键时的重复消息
+fn is_repeat(msg: &Message) -> bool {
+    matches!(
+        &msg.data,
+        MessageData::InputMessage(InputMessage {
+            info: InputInfo::Repeat { .. },
+            ..
+        })
+    )
+}
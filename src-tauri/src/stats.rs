@@ -0,0 +1,197 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::Sender;
+
+use crate::{
+    inputs::InputSource,
+    message::{Message, MessageData, MessageType},
+    CONFIG,
+};
+
+/// KPS和CPS的统计窗口
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+/// APM的统计窗口
+const APM_WINDOW: Duration = Duration::from_secs(60);
+/// 统计广播关闭时检查设置的间隔
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// 统计数据，用于广播和/stats
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatsMessage {
+    /// 每秒键盘按键数
+    pub kps: f64,
+    /// 每秒鼠标点击数
+    pub cps: f64,
+    /// 每分钟操作数(键盘按键和鼠标点击)
+    pub apm: f64,
+    /// 本次运行中的峰值
+    pub peak_kps: f64,
+    pub peak_cps: f64,
+    pub peak_apm: f64,
+    /// 本次运行中的总数
+    pub total_keys: u64,
+    pub total_clicks: u64,
+    /// 每个按键的按下次数
+    pub key_counts: BTreeMap<String, u64>,
+    /// 本次运行的时长(s)
+    pub session_secs: u64,
+    /// 被合并掉的鼠标移动消息数
+    pub coalesced_moves: u64,
+    /// 客户端接收过慢时丢弃的按键消息数
+    pub dropped_inputs: u64,
+    /// 客户端接收过慢时丢弃的鼠标移动消息数
+    pub dropped_moves: u64,
+}
+
+/// 统计状态
+struct Stats {
+    /// 统计窗口内的键盘按下时间
+    key_times: VecDeque<Instant>,
+    /// 统计窗口内的鼠标点击时间
+    click_times: VecDeque<Instant>,
+    /// APM窗口内的操作时间
+    action_times: VecDeque<Instant>,
+    peak_kps: f64,
+    peak_cps: f64,
+    peak_apm: f64,
+    total_keys: u64,
+    total_clicks: u64,
+    key_counts: BTreeMap<String, u64>,
+    started: Instant,
+    coalesced_moves: u64,
+    dropped_inputs: u64,
+    dropped_moves: u64,
+}
+
+static STATS: Lazy<Mutex<Stats>> = Lazy::new(|| {
+    Mutex::new(Stats {
+        key_times: VecDeque::new(),
+        click_times: VecDeque::new(),
+        action_times: VecDeque::new(),
+        peak_kps: 0.0,
+        peak_cps: 0.0,
+        peak_apm: 0.0,
+        total_keys: 0,
+        total_clicks: 0,
+        key_counts: BTreeMap::new(),
+        started: Instant::now(),
+        coalesced_moves: 0,
+        dropped_inputs: 0,
+        dropped_moves: 0,
+    })
+});
+
+impl Stats {
+    /// 去除窗口外的记录
+    fn expire(&mut self, now: Instant) {
+        for (times, window) in [
+            (&mut self.key_times, RATE_WINDOW),
+            (&mut self.click_times, RATE_WINDOW),
+            (&mut self.action_times, APM_WINDOW),
+        ] {
+            while let Some(time) = times.front() {
+                if now.duration_since(*time) < window {
+                    break;
+                }
+                times.pop_front();
+            }
+        }
+    }
+
+    /// 计算当前速率并更新峰值，返回(kps, cps, apm)
+    fn rates(&mut self, now: Instant) -> (f64, f64, f64) {
+        self.expire(now);
+        let kps = self.key_times.len() as f64 / RATE_WINDOW.as_secs_f64();
+        let cps = self.click_times.len() as f64 / RATE_WINDOW.as_secs_f64();
+        // 运行不满一分钟时按实际时长换算
+        let apm_secs = now
+            .duration_since(self.started)
+            .min(APM_WINDOW)
+            .as_secs_f64()
+            .max(RATE_WINDOW.as_secs_f64());
+        let apm = self.action_times.len() as f64 * 60.0 / apm_secs;
+        self.peak_kps = self.peak_kps.max(kps);
+        self.peak_cps = self.peak_cps.max(cps);
+        self.peak_apm = self.peak_apm.max(apm);
+        (kps, cps, apm)
+    }
+}
+
+/// 记录一次按下，由Handler::on_press调用
+pub fn record(name: &str, source: &InputSource) {
+    let now = Instant::now();
+    let mut stats = STATS.lock().unwrap();
+    match source {
+        InputSource::Keyboard => {
+            stats.key_times.push_back(now);
+            stats.total_keys += 1;
+        }
+        InputSource::MouseButton => {
+            stats.click_times.push_back(now);
+            stats.total_clicks += 1;
+        }
+        _ => return,
+    }
+    stats.action_times.push_back(now);
+    *stats.key_counts.entry(name.to_string()).or_insert(0) += 1;
+    stats.rates(now);
+}
+
+/// 记录一次被合并掉的鼠标移动
+pub fn record_coalesced_move() {
+    STATS.lock().unwrap().coalesced_moves += 1;
+}
+
+/// 记录客户端丢弃的消息数，motion为是否为鼠标移动消息
+pub fn record_dropped(count: u64, motion: bool) {
+    let mut stats = STATS.lock().unwrap();
+    if motion {
+        stats.dropped_moves += count;
+    } else {
+        stats.dropped_inputs += count;
+    }
+}
+
+/// 获取当前的统计数据
+pub fn snapshot() -> StatsMessage {
+    let now = Instant::now();
+    let mut stats = STATS.lock().unwrap();
+    let (kps, cps, apm) = stats.rates(now);
+    StatsMessage {
+        kps,
+        cps,
+        apm,
+        peak_kps: stats.peak_kps,
+        peak_cps: stats.peak_cps,
+        peak_apm: stats.peak_apm,
+        total_keys: stats.total_keys,
+        total_clicks: stats.total_clicks,
+        key_counts: stats.key_counts.clone(),
+        session_secs: now.duration_since(stats.started).as_secs(),
+        coalesced_moves: stats.coalesced_moves,
+        dropped_inputs: stats.dropped_inputs,
+        dropped_moves: stats.dropped_moves,
+    }
+}
+
+/// 按设置的间隔广播统计数据，间隔为0时不广播
+pub async fn run(sender: Sender<Message>) {
+    loop {
+        let interval = unsafe { CONFIG.lock().unwrap().stats_interval_ms };
+        if interval == 0 {
+            tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+            continue;
+        }
+        tokio::time::sleep(Duration::from_millis(interval)).await;
+        let _ = sender.send(Message {
+            r#type: MessageType::Stats,
+            data: MessageData::StatsMessage(snapshot()),
+        });
+    }
+}
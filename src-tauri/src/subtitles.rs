@@ -0,0 +1,476 @@
+use std::{collections::HashMap, path::Path};
+
+use serde::Serialize;
+
+use crate::{
+    file::write_file_atomic,
+    inputs::{get_modifier, modifier_order, InputInfo, InputSource},
+    keycast::get_label,
+    keys,
+    record::{read_recording, RecordedInput},
+};
+
+/// 字幕默认的最短显示时间(ms)
+pub const DEFAULT_MIN_DURATION_MS: u64 = 500;
+
+#[derive(Debug, Serialize)]
+pub enum SubtitleError {
+    ReadRecordingError,
+    UnknownFormatError,
+    WriteError,
+}
+
+/// 字幕格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubtitleFormat {
+    Srt,
+    WebVtt,
+    Ass,
+}
+
+impl SubtitleFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "srt" => Some(Self::Srt),
+            "vtt" | "webvtt" => Some(Self::WebVtt),
+            "ass" => Some(Self::Ass),
+            _ => None,
+        }
+    }
+
+    /// 通过文件扩展名判断格式
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::from_name(path.extension()?.to_str()?)
+    }
+}
+
+/// 导出选项
+#[derive(Debug, Clone)]
+pub struct SubtitleOptions {
+    pub format: SubtitleFormat,
+    /// 字幕最短显示时间(ms)，按下时间过短时延长到该时间
+    pub min_duration_ms: u64,
+    /// 是否将修饰键和按键合并为组合键(如"Ctrl+C")
+    pub merge_chords: bool,
+}
+
+/// 一条字幕
+#[derive(Debug, Clone)]
+pub struct Cue {
+    pub start_ms: u64,
+    pub end_ms: u64,
+    pub text: String,
+}
+
+/// 按住中的按键
+struct Held {
+    start_ms: u64,
+    /// 对应的字幕，修饰键在松开前没有字幕
+    cue: Option<usize>,
+    /// 修饰键是否已经合并到组合键中
+    used: bool,
+}
+
+/// 将录制的输入转换为字幕，每条字幕从按下持续到松开
+pub fn build_cues(inputs: &Vec<RecordedInput>, options: &SubtitleOptions) -> Vec<Cue> {
+    let mut cues: Vec<Cue> = Vec::new();
+    let mut held: HashMap<String, Held> = HashMap::new();
+    let last_offset = inputs.last().map(|input| input.offset_ms).unwrap_or(0);
+    for input in inputs.iter() {
+        let offset = input.offset_ms;
+        match (&input.message.source, &input.message.info) {
+            (
+                InputSource::Keyboard | InputSource::MouseButton,
+                InputInfo::Pressing {
+                    name,
+                    pressing: true,
+                    ..
+                },
+            ) => {
+                // 按住时的重复按下
+                if held.contains_key(name) {
+                    continue;
+                }
+                // 修饰键等到松开或按下其他按键时再决定是否显示
+                if options.merge_chords && get_modifier(name).is_some() {
+                    held.insert(
+                        name.clone(),
+                        Held {
+                            start_ms: offset,
+                            cue: None,
+                            used: false,
+                        },
+                    );
+                    continue;
+                }
+                let mut parts: Vec<String> = Vec::new();
+                if options.merge_chords {
+                    let mut modifiers: Vec<&str> = Vec::new();
+                    for (key, held) in held.iter_mut() {
+                        if let Some(modifier) = get_modifier(key) {
+                            modifiers.push(modifier);
+                            held.used = true;
+                        }
+                    }
+                    modifiers.sort_by_key(|modifier| modifier_order(modifier));
+                    modifiers.dedup();
+                    parts.extend(modifiers.iter().map(|modifier| get_label(modifier)));
+                }
+                parts.push(get_cue_label(name));
+                cues.push(Cue {
+                    start_ms: offset,
+                    end_ms: offset,
+                    text: parts.join("+"),
+                });
+                held.insert(
+                    name.clone(),
+                    Held {
+                        start_ms: offset,
+                        cue: Some(cues.len() - 1),
+                        used: false,
+                    },
+                );
+            }
+            (
+                InputSource::Keyboard | InputSource::MouseButton,
+                InputInfo::Pressing {
+                    name,
+                    pressing: false,
+                    ..
+                },
+            ) => {
+                if let Some(held) = held.remove(name) {
+                    release(&mut cues, name, held, offset);
+                }
+            }
+            _ => {}
+        }
+    }
+    // 录制结束时仍未松开的按键
+    for (name, held) in held {
+        release(&mut cues, &name, held, last_offset);
+    }
+    for cue in cues.iter_mut() {
+        cue.end_ms = cue.end_ms.max(cue.start_ms + options.min_duration_ms);
+    }
+    cues.sort_by_key(|cue| cue.start_ms);
+    cues
+}
+
+/// 松开按键时结束对应的字幕，单独按下的修饰键在此时生成字幕
+fn release(cues: &mut Vec<Cue>, name: &str, held: Held, offset: u64) {
+    match held.cue {
+        Some(index) => cues[index].end_ms = offset,
+        None if !held.used => cues.push(Cue {
+            start_ms: held.start_ms,
+            end_ms: offset,
+            text: get_cue_label(name),
+        }),
+        None => {}
+    }
+}
+
+/// 字幕中显示的按键文字
+fn get_cue_label(name: &str) -> String {
+    if name.eq(keys::MOUSE_LEFT) {
+        return "Left Click".to_string();
+    } else if name.eq(keys::MOUSE_RIGHT) {
+        return "Right Click".to_string();
+    } else if name.eq(keys::MOUSE_MIDDLE) {
+        return "Middle Click".to_string();
+    }
+    match get_modifier(name) {
+        Some(modifier) => get_label(modifier),
+        None => get_label(name),
+    }
+}
+
+/// 将字幕输出为指定格式的文本
+pub fn render(cues: &Vec<Cue>, format: SubtitleFormat) -> String {
+    match format {
+        SubtitleFormat::Srt => render_srt(cues),
+        SubtitleFormat::WebVtt => render_vtt(cues),
+        SubtitleFormat::Ass => render_ass(cues),
+    }
+}
+
+fn render_srt(cues: &Vec<Cue>) -> String {
+    let mut output = String::new();
+    for (index, cue) in cues.iter().enumerate() {
+        output.push_str(&format!(
+            "{}\n{} --> {}\n{}\n\n",
+            index + 1,
+            format_time(cue.start_ms, ','),
+            format_time(cue.end_ms, ','),
+            cue.text
+        ));
+    }
+    output
+}
+
+fn render_vtt(cues: &Vec<Cue>) -> String {
+    let mut output = String::from("WEBVTT\n\n");
+    for cue in cues.iter() {
+        output.push_str(&format!(
+            "{} --> {} align:end line:90%\n{}\n\n",
+            format_time(cue.start_ms, '.'),
+            format_time(cue.end_ms, '.'),
+            cue.text
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+        ));
+    }
+    output
+}
+
+/// ass字幕头，按键显示在右下角的半透明底框中
+static ASS_HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 1920
+PlayResY: 1080
+WrapStyle: 2
+ScaledBorderAndShadow: yes
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Key,Arial,56,&H00FFFFFF,&H00FFFFFF,&H80202020,&H80202020,-1,0,0,0,100,100,0,0,3,10,0,3,60,60,60,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+fn render_ass(cues: &Vec<Cue>) -> String {
+    let mut output = String::from(ASS_HEADER);
+    for cue in cues.iter() {
+        output.push_str(&format!(
+            "Dialogue: 0,{},{},Key,,0,0,0,,{}\n",
+            format_ass_time(cue.start_ms),
+            format_ass_time(cue.end_ms),
+            // 花括号和反斜杠在ass中有特殊含义
+            cue.text
+                .replace('\\', "＼")
+                .replace('{', "｛")
+                .replace('}', "｝")
+        ));
+    }
+    output
+}
+
+/// srt和vtt的时间格式 HH:MM:SS,mmm
+fn format_time(ms: u64, separator: char) -> String {
+    format!(
+        "{:02}:{:02}:{:02}{}{:03}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        separator,
+        ms % 1000
+    )
+}
+
+/// ass的时间格式 H:MM:SS.cc
+fn format_ass_time(ms: u64) -> String {
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        ms / 3_600_000,
+        ms / 60_000 % 60,
+        ms / 1000 % 60,
+        ms % 1000 / 10
+    )
+}
+
+/// 将录制文件导出为字幕文件，返回字幕条数
+pub fn export(
+    recording: &Path,
+    output: &Path,
+    options: &SubtitleOptions,
+) -> Result<usize, SubtitleError> {
+    let inputs = read_recording(recording).map_err(|_| SubtitleError::ReadRecordingError)?;
+    let cues = build_cues(&inputs, options);
+    let content = render(&cues, options.format);
+    write_file_atomic(output, content.as_bytes()).map_err(|_| SubtitleError::WriteError)?;
+    Ok(cues.len())
+}
+
+/// 导出字幕，未指定格式时通过输出文件的扩展名判断
+#[tauri::command]
+pub fn export_subtitles(
+    recording: String,
+    output: String,
+    format: Option<String>,
+    min_duration_ms: Option<u64>,
+    merge_chords: Option<bool>,
+) -> Result<usize, SubtitleError> {
+    let output = Path::new(&output);
+    let format = match format {
+        Some(format) => SubtitleFormat::from_name(&format),
+        None => SubtitleFormat::from_path(output),
+    }
+    .ok_or(SubtitleError::UnknownFormatError)?;
+    let options = SubtitleOptions {
+        format,
+        min_duration_ms: min_duration_ms.unwrap_or(DEFAULT_MIN_DURATION_MS),
+        merge_chords: merge_chords.unwrap_or(true),
+    };
+    export(Path::new(&recording), output, &options)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::SystemTime;
+
+    use super::*;
+    use crate::inputs::InputMessage;
+
+    /// 按键事件(按键名，是否按下，时间ms)转换为录制的输入
+    fn recording(events: &[(&str, bool, u64)]) -> Vec<RecordedInput> {
+        events
+            .iter()
+            .map(|(name, down, offset_ms)| RecordedInput {
+                offset_ms: *offset_ms,
+                message: InputMessage {
+                    source: if name.starts_with("mouse_") {
+                        InputSource::MouseButton
+                    } else {
+                        InputSource::Keyboard
+                    },
+                    info: InputInfo::Pressing {
+                        name: name.to_string(),
+                        pressing: *down,
+                        held_ms: None,
+                        physical: None,
+                        label: None,
+                    },
+                    time: SystemTime::now(),
+                    synthetic: false,
+                },
+            })
+            .collect()
+    }
+
+    fn options(merge_chords: bool) -> SubtitleOptions {
+        SubtitleOptions {
+            format: SubtitleFormat::Srt,
+            min_duration_ms: DEFAULT_MIN_DURATION_MS,
+            merge_chords,
+        }
+    }
+
+    fn describe(cues: &[Cue]) -> Vec<(u64, u64, &str)> {
+        cues.iter()
+            .map(|cue| (cue.start_ms, cue.end_ms, cue.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn cue_lasts_from_press_to_release() {
+        let inputs = recording(&[
+            (keys::KEY_A, true, 1000),
+            // 按住时的重复
+            (keys::KEY_A, true, 1500),
+            (keys::KEY_A, false, 2000),
+            (keys::MOUSE_LEFT, true, 3000),
+            (keys::MOUSE_LEFT, false, 3700),
+        ]);
+        let cues = build_cues(&inputs, &options(true));
+        assert_eq!(
+            describe(&cues),
+            vec![(1000, 2000, "A"), (3000, 3700, "Left Click")]
+        );
+    }
+
+    #[test]
+    fn short_presses_use_min_duration() {
+        let inputs = recording(&[(keys::ESCAPE, true, 0), (keys::ESCAPE, false, 80)]);
+        let cues = build_cues(&inputs, &options(true));
+        assert_eq!(describe(&cues), vec![(0, DEFAULT_MIN_DURATION_MS, "Esc")]);
+    }
+
+    #[test]
+    fn modifiers_are_merged_into_chords() {
+        let inputs = recording(&[
+            (keys::SHIFT_LEFT, true, 0),
+            (keys::CONTROL_RIGHT, true, 100),
+            (keys::KEY_C, true, 200),
+            (keys::KEY_C, false, 900),
+            (keys::CONTROL_RIGHT, false, 1000),
+            (keys::SHIFT_LEFT, false, 1100),
+            // 单独按下的修饰键在松开时显示
+            (keys::ALT_LEFT, true, 2000),
+            (keys::ALT_LEFT, false, 2600),
+        ]);
+        let cues = build_cues(&inputs, &options(true));
+        assert_eq!(
+            describe(&cues),
+            vec![(200, 900, "Ctrl+Shift+C"), (2000, 2600, "Alt")]
+        );
+        let cues = build_cues(&inputs, &options(false));
+        assert_eq!(
+            describe(&cues),
+            vec![
+                (0, 1100, "Shift"),
+                (100, 1000, "Ctrl"),
+                (200, 900, "C"),
+                (2000, 2600, "Alt"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keys_held_at_the_end_last_until_the_last_input() {
+        let inputs = recording(&[
+            (keys::SPACE, true, 0),
+            (keys::KEY_X, true, 1000),
+            (keys::KEY_X, false, 1600),
+        ]);
+        let cues = build_cues(&inputs, &options(true));
+        assert_eq!(describe(&cues), vec![(0, 1600, "Space"), (1000, 1600, "X")]);
+    }
+
+    #[test]
+    fn time_formats() {
+        assert_eq!(format_time(3_723_045, ','), "01:02:03,045");
+        assert_eq!(format_time(59_999, '.'), "00:00:59.999");
+        assert_eq!(format_ass_time(3_723_045), "1:02:03.04");
+    }
+
+    #[test]
+    fn rendered_formats() {
+        let cues = vec![
+            Cue {
+                start_ms: 0,
+                end_ms: 500,
+                text: "Ctrl+<".to_string(),
+            },
+            Cue {
+                start_ms: 1000,
+                end_ms: 1500,
+                text: "{".to_string(),
+            },
+        ];
+        assert_eq!(
+            render(&cues, SubtitleFormat::Srt),
+            "1\n00:00:00,000 --> 00:00:00,500\nCtrl+<\n\n2\n00:00:01,000 --> 00:00:01,500\n{\n\n"
+        );
+        let vtt = render(&cues, SubtitleFormat::WebVtt);
+        assert!(vtt.starts_with("WEBVTT\n\n00:00:00.000 --> 00:00:00.500"));
+        assert!(vtt.contains("\nCtrl+&lt;\n"));
+        let ass = render(&cues, SubtitleFormat::Ass);
+        assert!(ass.starts_with(ASS_HEADER));
+        assert!(ass.ends_with("Dialogue: 0,0:00:01.00,0:00:01.50,Key,,0,0,0,,｛\n"));
+    }
+
+    #[test]
+    fn formats_from_names() {
+        assert_eq!(
+            SubtitleFormat::from_name("VTT"),
+            Some(SubtitleFormat::WebVtt)
+        );
+        assert_eq!(
+            SubtitleFormat::from_path(Path::new("out/keys.ass")),
+            Some(SubtitleFormat::Ass)
+        );
+        assert_eq!(SubtitleFormat::from_path(Path::new("keys")), None);
+    }
+}
@@ -0,0 +1,67 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::ImageOutputFormat;
+use once_cell::sync::Lazy;
+
+/// 缩略图最大宽度(px)
+const THUMBNAIL_MAX_WIDTH: u32 = 240;
+/// 缩略图最大高度(px)
+const THUMBNAIL_MAX_HEIGHT: u32 = 160;
+
+/// 缓存的缩略图
+struct CachedThumbnail {
+    /// 生成缩略图时图片的修改时间
+    modified: SystemTime,
+    /// 生成缩略图时图片的大小
+    len: u64,
+    /// png格式的data uri
+    data_uri: String,
+}
+
+/// 缩略图缓存，键为图片路径
+static THUMBNAILS: Lazy<Mutex<HashMap<PathBuf, CachedThumbnail>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 获取图片的缩略图data uri，图片修改后重新生成
+pub fn get_thumbnail(path: &Path) -> Option<String> {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?;
+    let len = metadata.len();
+    if let Some(cached) = THUMBNAILS.lock().unwrap().get(path) {
+        if cached.modified == modified && cached.len == len {
+            return Some(cached.data_uri.clone());
+        }
+    }
+    // 缓存不存在或已失效，在锁外生成缩略图
+    let data_uri = create_thumbnail(path)?;
+    THUMBNAILS.lock().unwrap().insert(
+        path.to_path_buf(),
+        CachedThumbnail {
+            modified,
+            len,
+            data_uri: data_uri.clone(),
+        },
+    );
+    Some(data_uri)
+}
+
+/// 生成缩小后的png并编码为data uri
+fn create_thumbnail(path: &Path) -> Option<String> {
+    let data = fs::read(path).ok()?;
+    let image = image::load_from_memory(&data).ok()?;
+    let thumbnail = image.thumbnail(THUMBNAIL_MAX_WIDTH, THUMBNAIL_MAX_HEIGHT);
+    let mut png = Cursor::new(Vec::new());
+    thumbnail.write_to(&mut png, ImageOutputFormat::Png).ok()?;
+    Some(format!(
+        "data:image/png;base64,{}",
+        STANDARD.encode(png.into_inner())
+    ))
+}
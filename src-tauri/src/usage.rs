@@ -0,0 +1,257 @@
+use std::{collections::BTreeMap, fs, io::Cursor, path::Path, sync::Mutex, time::Duration};
+
+use chrono::{Local, NaiveDate};
+use image::{ImageOutputFormat, Rgba};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    constants,
+    file::write_file_atomic,
+    inputs::InputSource,
+    preset::{resolve_image, resolve_manifest},
+    CONFIG,
+};
+
+/// 按键次数写入文件的间隔
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+/// 日期格式
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Serialize)]
+pub enum UsageError {
+    InvalidDateError,
+    NoPresetError,
+    PresetError,
+    ImageError,
+    WriteError,
+}
+
+/// 单日的按键次数
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DayUsage {
+    #[serde(default)]
+    pub keys: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub mouse_buttons: BTreeMap<String, u64>,
+}
+
+/// 按日期范围查询的结果
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// 范围内的按键总次数
+    pub keys: BTreeMap<String, u64>,
+    /// 范围内的鼠标按键总次数
+    pub mouse_buttons: BTreeMap<String, u64>,
+    /// 每天的按键次数，键为日期(YYYY-MM-DD)
+    pub days: BTreeMap<String, DayUsage>,
+}
+
+struct Usage {
+    /// 键为日期(YYYY-MM-DD)
+    days: BTreeMap<String, DayUsage>,
+    /// 是否有未写入文件的记录
+    dirty: bool,
+}
+
+static USAGE: Lazy<Mutex<Usage>> = Lazy::new(|| {
+    // 读取按键次数文件，文件不存在或格式错误时从空记录开始
+    let days = fs::read_to_string(constants::USAGE_FILE_PATH)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    Mutex::new(Usage { days, dirty: false })
+});
+
+/// 记录一次按下，由Handler::on_press调用
+pub fn record(name: &str, source: &InputSource) {
+    let today = Local::now().format(DATE_FORMAT).to_string();
+    let mut usage = USAGE.lock().unwrap();
+    let day = usage.days.entry(today).or_default();
+    let counts = match source {
+        InputSource::Keyboard => &mut day.keys,
+        InputSource::MouseButton => &mut day.mouse_buttons,
+        _ => return,
+    };
+    *counts.entry(name.to_string()).or_insert(0) += 1;
+    usage.dirty = true;
+}
+
+/// 将按键次数写入文件
+/// 写入时不持有锁，避免阻塞按键记录
+pub fn save() -> bool {
+    let content = {
+        let mut usage = USAGE.lock().unwrap();
+        if !usage.dirty {
+            return true;
+        }
+        usage.dirty = false;
+        serde_json::to_string(&usage.days).unwrap()
+    };
+    if let Err(error) = write_file_atomic(Path::new(constants::USAGE_FILE_PATH), content.as_bytes())
+    {
+        eprintln!("{:?}", error);
+        // 下次重新写入
+        USAGE.lock().unwrap().dirty = true;
+        return false;
+    }
+    true
+}
+
+/// 定时将按键次数写入文件
+pub async fn run() {
+    loop {
+        tokio::time::sleep(SAVE_INTERVAL).await;
+        tokio::task::spawn_blocking(save).await.unwrap_or(false);
+    }
+}
+
+/// 查询日期范围内(包含首尾)的按键次数，范围为空时不限制
+pub fn query(from: Option<String>, to: Option<String>) -> Result<UsageReport, UsageError> {
+    let (from, to) = parse_range(from, to)?;
+    let usage = USAGE.lock().unwrap();
+    let mut report = UsageReport::default();
+    for (date, day) in usage.days.iter() {
+        if !in_range(date, &from, &to) {
+            continue;
+        }
+        for (name, count) in day.keys.iter() {
+            *report.keys.entry(name.clone()).or_insert(0) += count;
+        }
+        for (name, count) in day.mouse_buttons.iter() {
+            *report.mouse_buttons.entry(name.clone()).or_insert(0) += count;
+        }
+        report.days.insert(date.clone(), day.clone());
+    }
+    Ok(report)
+}
+
+/// 清除日期范围内(包含首尾)的按键次数，范围为空时全部清除
+pub fn reset(from: Option<String>, to: Option<String>) -> Result<(), UsageError> {
+    let (from, to) = parse_range(from, to)?;
+    {
+        let mut usage = USAGE.lock().unwrap();
+        usage.days.retain(|date, _| !in_range(date, &from, &to));
+        usage.dirty = true;
+    }
+    if save() {
+        Ok(())
+    } else {
+        Err(UsageError::WriteError)
+    }
+}
+
+/// 将日期范围内的按键次数以热力图的形式绘制在当前预设的图片上，返回png数据
+pub fn render_heatmap(from: Option<String>, to: Option<String>) -> Result<Vec<u8>, UsageError> {
+    let report = query(from, to)?;
+    let preset =
+        unsafe { CONFIG.lock().unwrap().preset.clone() }.ok_or(UsageError::NoPresetError)?;
+    let manifest = resolve_manifest(&preset).map_err(|_| UsageError::PresetError)?;
+    let image_path = resolve_image(&preset).map_err(|_| UsageError::PresetError)?;
+    let data = fs::read(image_path).map_err(|_| UsageError::ImageError)?;
+    let mut image = image::load_from_memory(&data)
+        .map_err(|_| UsageError::ImageError)?
+        .to_rgba8();
+
+    let get_count = |name: &String| {
+        report
+            .keys
+            .get(name)
+            .or(report.mouse_buttons.get(name))
+            .copied()
+            .unwrap_or(0)
+    };
+    let max = manifest
+        .keys
+        .iter()
+        .map(|region| get_count(&region.name))
+        .max()
+        .unwrap_or(0);
+    if max > 0 {
+        for region in manifest.keys.iter() {
+            let count = get_count(&region.name);
+            if count == 0 {
+                continue;
+            }
+            let (color, alpha) = heat_color(count as f32 / max as f32);
+            let x_end = (region.x + region.width).min(image.width());
+            let y_end = (region.y + region.height).min(image.height());
+            for y in region.y..y_end {
+                for x in region.x..x_end {
+                    blend(image.get_pixel_mut(x, y), color, alpha);
+                }
+            }
+        }
+    }
+
+    let mut png = Cursor::new(Vec::new());
+    image
+        .write_to(&mut png, ImageOutputFormat::Png)
+        .map_err(|_| UsageError::ImageError)?;
+    Ok(png.into_inner())
+}
+
+/// 查询按键次数
+#[tauri::command]
+pub fn get_usage(from: Option<String>, to: Option<String>) -> Result<UsageReport, UsageError> {
+    query(from, to)
+}
+
+/// 清除按键次数
+#[tauri::command]
+pub fn reset_usage(from: Option<String>, to: Option<String>) -> Result<(), UsageError> {
+    reset(from, to)
+}
+
+/// 导出热力图到指定路径
+#[tauri::command]
+pub fn export_heatmap(
+    path: String,
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(), UsageError> {
+    let png = render_heatmap(from, to)?;
+    write_file_atomic(Path::new(&path), &png).map_err(|_| UsageError::WriteError)
+}
+
+/// 解析日期范围
+fn parse_range(
+    from: Option<String>,
+    to: Option<String>,
+) -> Result<(Option<NaiveDate>, Option<NaiveDate>), UsageError> {
+    let parse = |date: Option<String>| match date {
+        Some(date) => NaiveDate::parse_from_str(&date, DATE_FORMAT)
+            .map(Some)
+            .map_err(|_| UsageError::InvalidDateError),
+        None => Ok(None),
+    };
+    Ok((parse(from)?, parse(to)?))
+}
+
+/// 判断记录中的日期是否在范围内
+fn in_range(date: &str, from: &Option<NaiveDate>, to: &Option<NaiveDate>) -> bool {
+    let date = match NaiveDate::parse_from_str(date, DATE_FORMAT) {
+        Ok(date) => date,
+        Err(_) => return false,
+    };
+    from.map_or(true, |from| date >= from) && to.map_or(true, |to| date <= to)
+}
+
+/// 热力颜色，次数越多越接近红色且越不透明
+fn heat_color(intensity: f32) -> ([f32; 3], f32) {
+    let t = intensity.clamp(0.0, 1.0);
+    let color = [
+        255.0 * t,
+        160.0 * (1.0 - (2.0 * t - 1.0).abs()),
+        255.0 * (1.0 - t),
+    ];
+    (color, 0.25 + 0.5 * t)
+}
+
+/// 混合颜色到像素上
+fn blend(pixel: &mut Rgba<u8>, color: [f32; 3], alpha: f32) {
+    for i in 0..3 {
+        pixel[i] = (pixel[i] as f32 * (1.0 - alpha) + color[i] * alpha).round() as u8;
+    }
+    pixel[3] = pixel[3].max((alpha * 255.0) as u8);
+}
@@ -0,0 +1,76 @@
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::{inputs::Handler, CONFIG};
+
+/// 检查按住状态的间隔
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// 两次检查的实际间隔超出CHECK_INTERVAL该时间以上时视为系统休眠过
+const SUSPEND_GAP: Duration = Duration::from_secs(5);
+
+/// 定时检查按住的按键，恢复因松开事件丢失而卡住的按键
+/// - 总开关切换时松开所有按键，关闭期间的输入不会被处理
+/// - 系统休眠唤醒后松开所有按键，休眠通过检查间隔中系统时间的跳跃判断
+/// - Windows下锁屏、UAC提示等切换到安全桌面时松开所有按键
+/// - 按住超过设置的最长时间的按键松开，正在重复的按键从最后一次重复开始计算
+///
+/// Linux和macOS下没有检测锁屏，锁屏时按住的按键在超过最长时间后松开
+pub fn run(handler: Arc<Mutex<Handler>>) {
+    let mut last_enable = unsafe { CONFIG.lock().unwrap().enable };
+    let mut last_check = SystemTime::now();
+    let mut last_locked = is_locked();
+    loop {
+        thread::sleep(CHECK_INTERVAL);
+        let (enable, max_hold_ms) = unsafe {
+            let config = CONFIG.lock().unwrap();
+            (config.enable, config.max_key_hold_ms)
+        };
+        let now = SystemTime::now();
+        // 休眠期间线程不运行，但系统时间会继续走
+        let suspended =
+            now.duration_since(last_check).unwrap_or(Duration::ZERO) > CHECK_INTERVAL + SUSPEND_GAP;
+        last_check = now;
+        let locked = is_locked();
+        let just_locked = locked && !last_locked;
+        last_locked = locked;
+        let mut handler = handler.lock().unwrap();
+        if enable != last_enable || suspended || just_locked {
+            last_enable = enable;
+            handler.release_all(now);
+            continue;
+        }
+        if enable && max_hold_ms > 0 {
+            handler.expire_keys(now, Duration::from_millis(max_hold_ms));
+        }
+    }
+}
+
+/// 输入桌面是否为锁屏或UAC提示等安全桌面，此时键盘钩子收不到输入
+#[cfg(target_os = "windows")]
+fn is_locked() -> bool {
+    #[link(name = "user32")]
+    extern "system" {
+        fn OpenInputDesktop(flags: u32, inherit: i32, desired_access: u32) -> isize;
+        fn SwitchDesktop(desktop: isize) -> i32;
+        fn CloseDesktop(desktop: isize) -> i32;
+    }
+    const DESKTOP_SWITCHDESKTOP: u32 = 0x0100;
+    unsafe {
+        let desktop = OpenInputDesktop(0, 0, DESKTOP_SWITCHDESKTOP);
+        if desktop == 0 {
+            return true;
+        }
+        // 普通桌面为当前输入桌面时切换成功，安全桌面无法切换
+        let locked = SwitchDesktop(desktop) == 0;
+        CloseDesktop(desktop);
+        locked
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn is_locked() -> bool {
+    false
+}
@@ -15,7 +15,8 @@ let config = {
     key_up_transition_duration: 100,
     mouse_move_enable: false,
     mouse_move_radius_px: 50,
-    mouse_move_transition_duration: 100
+    mouse_move_transition_duration: 100,
+    keep_user_overrides: false
 };
 /**
  * 服务器端口号
@@ -251,14 +252,7 @@ function initConfigs() {
     }
     mainSwitchAnimation();
 
-    keyDownSlider.setValue(config.key_down_transition_duration);
-    keyUpSlider.setValue(config.key_up_transition_duration);
-    mouseRadiusSlider.setValue(config.mouse_move_radius_px);
-    mouseMoveSlider.setValue(config.mouse_move_transition_duration);
-    rangeObject.key_down_transition_duration.old = config.key_down_transition_duration;
-    rangeObject.key_up_transition_duration.old = config.key_up_transition_duration;
-    rangeObject.mouse_move_radius_px.old = config.mouse_move_radius_px;
-    rangeObject.mouse_move_transition_duration.old = config.mouse_move_transition_duration;
+    initSliders();
 
     versionText.innerText = `v${version}`;
 
@@ -269,6 +263,20 @@ function initConfigs() {
     }
 }
 
+/**
+ * @description: 根据设置初始化滚动条
+ */
+function initSliders() {
+    keyDownSlider.setValue(config.key_down_transition_duration);
+    keyUpSlider.setValue(config.key_up_transition_duration);
+    mouseRadiusSlider.setValue(config.mouse_move_radius_px);
+    mouseMoveSlider.setValue(config.mouse_move_transition_duration);
+    rangeObject.key_down_transition_duration.old = config.key_down_transition_duration;
+    rangeObject.key_up_transition_duration.old = config.key_up_transition_duration;
+    rangeObject.mouse_move_radius_px.old = config.mouse_move_radius_px;
+    rangeObject.mouse_move_transition_duration.old = config.mouse_move_transition_duration;
+}
+
 /**
  * @description: 更改预设后
  */
@@ -276,6 +284,9 @@ async function presetChanged() {
     let saved = await updateConfig('preset', presetSelect.options[presetSelect.selectedIndex].value);
     if (saved) {
         lastPreset = presetSelect.options[presetSelect.selectedIndex].value;
+        // 新预设可能带有推荐设置，重新获取生效的设置
+        config = await getConfig();
+        initSliders();
     } else {
         for (let i = 0; i < presetSelect.options.length; i++) {
             if (presetSelect.options[i].value == lastPreset) {